@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
 pub enum Alignment {
     #[default]
     Left,
@@ -8,3 +8,13 @@ pub enum Alignment {
     Right,
     Justified,
 }
+
+// Alignment of the children of a row along the vertical axis
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum VerticalAlignment {
+    #[default]
+    Top,
+    Center,
+    Bottom,
+    Baseline,
+}
@@ -1,13 +1,20 @@
-use std::io::{Error, ErrorKind};
+use std::collections::HashMap;
+use std::io::Error;
 
 use crate::{
     data_schema::DataSchema,
+    diagnostic::Diagnostic,
+    direction::Direction,
     element::Element,
     font::{FontDict, FontLoader},
+    font_registry::FontRegistry,
     layout_schema::LayoutSchema,
+    point::Point,
     resume_data::ResumeData,
     resume_layout::{ColumnType, ResumeLayout},
+    shape::Shape,
     spatial_box::SpatialBox,
+    theme::Theme,
 };
 
 pub struct AnyLayout;
@@ -16,6 +23,8 @@ pub struct AnyLayout;
 pub struct ElementBox {
     pub bounding_box: SpatialBox,
     pub elements: Vec<(SpatialBox, Element)>,
+    // Timeline entries are split at page breaks instead of moving to the next page whole
+    pub breakable: bool,
 }
 
 impl ElementBox {
@@ -38,6 +47,23 @@ impl ElementBox {
             .collect::<Vec<_>>();
         self
     }
+
+    // Mirrors the elements horizontally inside the bounding box, for right-to-left sections
+    pub fn mirror_x(&mut self) -> &mut ElementBox {
+        let left = self.bounding_box.top_left.x;
+        let right = self.bounding_box.bottom_right.x;
+        self.elements = self
+            .elements
+            .iter_mut()
+            .map(|(b, e)| {
+                (
+                    b.move_x_by(left + right - b.bottom_right.x - b.top_left.x),
+                    e.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        self
+    }
 }
 
 impl ElementBox {
@@ -45,61 +71,169 @@ impl ElementBox {
         ElementBox {
             bounding_box,
             elements,
+            breakable: false,
         }
     }
+
+    /// Splits the box at the page break `y`, the elements below it continue in a second box
+    /// starting at 0. Elements crossing the break are not cut and the split moves above
+    /// them, except vertical lines such as the spine of a timeline, which run to the break
+    /// and continue at the top of the second box. Returns `None` if nothing fits above it.
+    pub fn split_at(&self, y: f32) -> Option<(ElementBox, ElementBox)> {
+        let is_vertical_line =
+            |e: &Element| matches!(e.shape, Some(Shape::Line { vertical: true, .. }));
+        let crossing = |cut: f32| {
+            self.elements
+                .iter()
+                .filter(|(b, e)| {
+                    !is_vertical_line(e) && b.top_left.y < cut && b.bottom_right.y > cut
+                })
+                .map(|(b, _)| b.top_left.y)
+                .reduce(f32::min)
+        };
+        // Moving the split above an element can make it cross another one
+        let mut cut = y;
+        while let Some(top) = crossing(cut) {
+            cut = top;
+        }
+        if cut <= self.bounding_box.top_left.y {
+            return None;
+        }
+
+        let (left, right) = (
+            self.bounding_box.top_left.x,
+            self.bounding_box.bottom_right.x,
+        );
+        let mut head = ElementBox {
+            bounding_box: SpatialBox::new(self.bounding_box.top_left, Point::new(right, cut)),
+            elements: Vec::new(),
+            breakable: self.breakable,
+        };
+        let mut tail = ElementBox {
+            bounding_box: SpatialBox::new(Point::new(left, cut), self.bounding_box.bottom_right),
+            elements: Vec::new(),
+            breakable: self.breakable,
+        };
+        for (b, e) in self.elements.iter() {
+            if b.bottom_right.y <= cut {
+                head.elements.push((*b, e.clone()));
+            } else if b.top_left.y >= cut {
+                tail.elements.push((*b, e.clone()));
+            } else {
+                let end = b.bottom_right.y.min(y);
+                head.elements.push((
+                    SpatialBox::new(b.top_left, Point::new(b.bottom_right.x, end)),
+                    e.clone(),
+                ));
+                tail.elements.push((
+                    SpatialBox::new(Point::new(b.top_left.x, cut), b.bottom_right),
+                    e.clone(),
+                ));
+            }
+        }
+        tail.move_y_by(-cut);
+        Some((head, tail))
+    }
 }
 
 impl AnyLayout {
+    /// Lays out the sections of the resume into pages. Problems the layout recovered from,
+    /// such as values that do not match their data schema or text that does not fit, are
+    /// returned with the pages.
     pub fn render(
         layout_schemas: &[LayoutSchema],
         resume_data: &ResumeData,
         data_schemas: &[DataSchema],
         resume_layout: &ResumeLayout,
-    ) -> std::io::Result<(FontDict, Vec<Vec<ElementBox>>)> {
+        theme: &Theme,
+        font_registry: &FontRegistry,
+    ) -> std::io::Result<(FontDict, Vec<Vec<ElementBox>>, Vec<Diagnostic>)> {
         // Font dictionary is used for font caching
         let mut font_dict: FontDict = FontDict::new();
-        // Each box contains a set of elements(positioned by 0x0 and projected into its bounding box)
-        let mut boxes: Vec<ElementBox> = Vec::new();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        // Each box contains a set of elements(positioned by 0x0 and projected into its bounding box),
+        // boxes are grouped by the section they belong to
+        let mut sections: Vec<Vec<ElementBox>> = Vec::new();
+
+        // All page measurements are in points
+        let page_height = resume_layout.page_height()?;
+        let margin = resume_layout.page_margin();
 
         // Compute the total usable width by subtracting the margins from the document width
-        let width = resume_layout.width - (resume_layout.margin.left + resume_layout.margin.right);
+        let width = resume_layout.page_width()? - (margin.left + margin.right);
 
         // If the resume is double column, then the usable width is halved
         let column_width = match resume_layout.column_type {
             ColumnType::SingleColumn => width,
-            ColumnType::DoubleColumn { vertical_margin } => (width - vertical_margin) / 2.0,
+            ColumnType::DoubleColumn { .. } => (width - resume_layout.vertical_margin()) / 2.0,
         };
 
+        // Styles are resolved before the fonts are loaded, the document-wide fallbacks come
+        // after the fallbacks of each font
+        let styles = theme.styles_under(&resume_layout.styles);
+        let layout_schemas = layout_schemas
+            .iter()
+            .map(|s| {
+                s.resolve_styles(&styles)
+                    .inherit_font_fallbacks(&resume_layout.font_fallbacks)
+            })
+            .collect::<Vec<LayoutSchema>>();
+
         for section in &resume_data.sections {
+            let mut boxes: Vec<ElementBox> = Vec::new();
             // Render Section Header
             // 1. Find the layout schema for the section
             log::info!("Computing section: {}", section.section_name);
 
             let Some(layout_schema) = layout_schemas
-                    .iter()
-                    .find(|&s| s.schema_name == section.layout_schema)
-                else {
-                    return Err(Error::new(ErrorKind::Other, format!("SectionLayout not found for {}", section.layout_schema)));
-                };
+                .iter()
+                .find(|&s| s.schema_name == section.layout_schema)
+            else {
+                return Err(Error::other(format!(
+                    "SectionLayout not found for {}",
+                    section.layout_schema
+                )));
+            };
+
+            font_dict.load_fonts_from_schema(layout_schema, font_registry);
 
-            font_dict.load_fonts_from_schema(layout_schema);
+            // Sections follow the direction of the document unless they override it
+            let direction = section.direction.unwrap_or(resume_layout.direction);
 
             // 2. Find the data schema for the section
-            let _data_schema = data_schemas
+            let data_schema = data_schemas
                 .iter()
                 .find(|&s| s.schema_name == section.data_schema)
                 .unwrap();
+            for message in DataSchema::validate(&data_schema.header_schema, &section.data) {
+                diagnostics.push(
+                    Diagnostic::new("header".to_string(), message)
+                        .in_section(&section.section_name),
+                );
+            }
             // 3. Render the header
 
-            let result = layout_schema
+            let (header, header_diagnostics) = layout_schema
                 .header_layout_schema
                 .instantiate(&section.data)
-                .normalize(column_width, &font_dict)
-                .compute_boxes(&font_dict);
+                .with_direction(direction)
+                .inherit_hyphenation(&layout_schema.hyphenation)
+                .normalize_with_diagnostics(column_width, &font_dict);
+            diagnostics.extend(
+                header_diagnostics
+                    .iter()
+                    .map(|d| d.in_section(&section.section_name)),
+            );
+            let mut result = header.compute_boxes(&font_dict);
+
+            if direction == Direction::Rtl {
+                result.mirror_x();
+            }
 
             boxes.push(result);
 
             // Render Section Items
+            let mut items = Vec::new();
             for (index, item) in section.items.iter().enumerate() {
                 log::info!("Computing item {index}");
                 // 1. Find the layout schema for the section
@@ -108,48 +242,214 @@ impl AnyLayout {
                     .find(|&s| s.schema_name == section.layout_schema)
                     .unwrap();
 
-                font_dict.load_fonts_from_schema(layout_schema);
+                font_dict.load_fonts_from_schema(layout_schema, font_registry);
 
                 // 2. Find the data schema for the section
-                let _data_schema = data_schemas
+                let data_schema = data_schemas
                     .iter()
                     .find(|&s| s.schema_name == section.data_schema)
                     .unwrap();
-                // 3. Render the item
-                let result = layout_schema
-                    .item_layout_schema
-                    .instantiate(item)
-                    .normalize(column_width, &font_dict)
-                    .compute_boxes(&font_dict);
+                for message in DataSchema::validate(&data_schema.item_schema, item) {
+                    diagnostics.push(
+                        Diagnostic::new(format!("item {}", index), message)
+                            .in_section(&section.section_name),
+                    );
+                }
+                // 3. Instantiate the item
+                items.push(
+                    layout_schema
+                        .item_layout_schema
+                        .instantiate(item)
+                        .with_direction(direction)
+                        .inherit_hyphenation(&layout_schema.hyphenation)
+                        .scale_width(column_width),
+                );
+            }
+
+            // 4. Line up the columns of the grids across the items
+            let mut grid_widths = HashMap::new();
+            for item in items.iter() {
+                item.measure_grids(&font_dict, &mut grid_widths);
+            }
+
+            // 5. Render the items
+            for item in items {
+                let (item, item_diagnostics) = item
+                    .with_section_widths(&grid_widths)
+                    .normalize_with_diagnostics(column_width, &font_dict);
+                diagnostics.extend(
+                    item_diagnostics
+                        .iter()
+                        .map(|d| d.in_section(&section.section_name)),
+                );
+                let mut result = item.compute_boxes(&font_dict);
+
+                if direction == Direction::Rtl {
+                    result.mirror_x();
+                }
 
                 boxes.push(result);
             }
+
+            sections.push(boxes);
         }
 
-        let mut current_y = resume_layout.margin.top;
-        let mut current_x = resume_layout.margin.left;
+        let mut current_y = margin.top;
+        let mut current_x = margin.left;
 
         let mut pages: Vec<Vec<ElementBox>> = Vec::new();
         pages.push(Vec::new());
 
-        boxes.iter_mut().for_each(|b| {
-            if current_y + b.bounding_box.height() > resume_layout.height {
-                current_y = resume_layout.margin.top;
-                current_x += column_width + resume_layout.column_type.vertical_margin();
-                if current_x > width {
-                    pages.push(Vec::new());
-                    current_x = resume_layout.margin.left;
+        let next_column = |current_x: &mut f32, current_y: &mut f32, pages: &mut Vec<_>| {
+            *current_y = margin.top;
+            *current_x += column_width + resume_layout.vertical_margin();
+            if *current_x > width {
+                pages.push(Vec::new());
+                *current_x = margin.left;
+            }
+        };
+
+        for (index, boxes) in sections.iter().enumerate() {
+            // Sections are spaced apart, except at the top of a column
+            if index > 0 && current_y > margin.top {
+                current_y += resume_layout.section_spacing();
+            }
+
+            for b in boxes {
+                let mut next = Some(b.clone());
+                while let Some(mut b) = next.take() {
+                    let available = page_height - margin.bottom - current_y;
+                    if b.bounding_box.height() > available {
+                        // Breakable boxes leave the part that fits and continue in the next
+                        // column, others move there whole
+                        let split = if b.breakable {
+                            b.split_at(b.bounding_box.top_left.y + available)
+                        } else {
+                            None
+                        };
+                        match split {
+                            Some((head, tail)) => {
+                                b = head;
+                                next = Some(tail);
+                            }
+                            None => next_column(&mut current_x, &mut current_y, &mut pages),
+                        }
+                    }
+                    pages
+                        .last_mut()
+                        .unwrap()
+                        .push(b.move_y_by(current_y).move_x_by(current_x).clone());
+                    current_y += b.bounding_box.height();
+                    if next.is_some() {
+                        next_column(&mut current_x, &mut current_y, &mut pages);
+                    }
                 }
             }
-            pages
-                .last_mut()
-                .unwrap()
-                .push(b.move_y_by(current_y).move_x_by(current_x).clone());
-            current_y += b.bounding_box.height();
-        });
+        }
 
         log::info!("Position calculations are completed.");
 
-        Ok((font_dict, pages))
+        Ok((font_dict, pages, diagnostics))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_timeline_across_pages() {
+        let layout_schemas = LayoutSchema::from_json(
+            r#"[{
+                "schema_name": "Jobs",
+                "header_layout_schema": { "Text": { "item": "Jobs", "style": "body" } },
+                "item_layout_schema": {
+                    "Timeline": {
+                        "date": { "Ref": { "item": "Date", "style": "body" } },
+                        "elements": [{ "Ref": { "item": "Text", "style": "body" } }]
+                    }
+                }
+            }]"#,
+        );
+        let data_schemas = DataSchema::from_json(
+            r#"[{
+                "schema_name": "Jobs",
+                "header_schema": [],
+                "item_schema": [
+                    { "name": "Date", "type": "String" },
+                    { "name": "Text", "type": "String" }
+                ]
+            }]"#,
+        );
+        let resume_layout = &ResumeLayout::from_json(
+            r#"[{
+                "schema_name": "Small",
+                "column_type": "SingleColumn",
+                "page_size": { "Custom": { "width": 300, "height": 200 } },
+                "unit": "Pt",
+                "margin": { "top": 20, "bottom": 20, "left": 20, "right": 20 },
+                "styles": {
+                    "body": { "font": { "name": "Exo", "size": 10.0, "source": "Local" } }
+                }
+            }]"#,
+        )[0];
+        let text = "worked on the layout engine of a resume builder ".repeat(12);
+        let resume_data = ResumeData::from_json(&format!(
+            r#"{{
+                "layout": "Small",
+                "sections": [{{
+                    "section_name": "Jobs",
+                    "data_schema": "Jobs",
+                    "layout_schema": "Jobs",
+                    "data": {{}},
+                    "items": [
+                        {{ "Date": "2023", "Text": "Engineer" }},
+                        {{ "Date": "2021", "Text": "{}" }}
+                    ]
+                }}]
+            }}"#,
+            text
+        ));
+        let font_registry = FontRegistry::new(vec![PathBuf::from("assets")]);
+
+        let (_, pages, _) = AnyLayout::render(
+            &layout_schemas,
+            &resume_data,
+            &data_schemas,
+            resume_layout,
+            &Theme::default(),
+            &font_registry,
+        )
+        .unwrap();
+        assert_eq!(pages.len(), 2);
+
+        let is_spine = |e: &Element| matches!(e.shape, Some(Shape::Line { vertical: true, .. }));
+        let spines = |page: &Vec<ElementBox>| {
+            page.iter()
+                .flat_map(|b| b.elements.iter())
+                .filter(|(_, e)| is_spine(e))
+                .map(|(b, _)| *b)
+                .collect::<Vec<SpatialBox>>()
+        };
+        let lines = |page: &Vec<ElementBox>| {
+            page.iter()
+                .flat_map(|b| b.elements.iter())
+                .filter(|(_, e)| !e.item.is_empty())
+                .map(|(b, _)| *b)
+                .collect::<Vec<SpatialBox>>()
+        };
+
+        // The second entry starts on the first page and continues on the second one, its
+        // spine runs to the bottom margin and starts again at the top margin
+        assert_eq!(spines(&pages[0]).len(), 2);
+        assert_eq!(spines(&pages[0])[1].bottom_right.y, 180.0);
+        assert_eq!(spines(&pages[1]).len(), 1);
+        assert_eq!(spines(&pages[1])[0].top_left.y, 20.0);
+        assert!(lines(&pages[0]).iter().all(|b| b.bottom_right.y <= 180.0));
+        assert!(lines(&pages[1]).iter().all(|b| b.top_left.y >= 20.0));
+        assert!(lines(&pages[0]).len() > 3);
+        assert!(!lines(&pages[1]).is_empty());
     }
 }
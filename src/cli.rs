@@ -1,26 +1,45 @@
-#![feature(file_create_new)]
-
 mod alignment;
 mod any_layout;
 mod basic_layout;
+mod color;
 mod container;
 mod data_schema;
+mod diagnostic;
+mod direction;
 mod element;
 mod font;
+mod font_registry;
+mod font_subset;
+mod grid;
+mod height;
+mod hyphenation;
+mod icon;
+mod image_element;
 mod layout;
 mod layout_schema;
 mod local_storage;
 mod margin;
+mod meter;
+mod overflow;
 mod pdf_layout;
 mod png_layout;
 mod point;
 mod resume_data;
 mod resume_layout;
+mod shape;
+mod shaping;
 mod spatial_box;
+mod style;
+mod text_transform;
+mod theme;
+mod timeline;
+mod unit;
+mod variable_font;
 mod width;
 
 use std::fs;
 
+use font_registry::FontRegistry;
 use pdf_layout::PdfLayout;
 use png_layout::PngLayout;
 use std::path::Path;
@@ -30,29 +49,81 @@ use std::env;
 fn main() {
     env_logger::init();
 
-    let resume_path = env::args().nth(1).expect("No resume path provided");
+    // `cli fonts [storage dir]` lists the local fonts found in the font search paths
+    if env::args().nth(1).as_deref() == Some("fonts") {
+        let local_storage = if let Some(dir) = env::args().nth(2) {
+            local_storage::LocalStorage::custom_dir(dir.as_str())
+        } else {
+            local_storage::LocalStorage::new()
+        };
+        local_storage.initiate_local_storage();
+        list_fonts(&local_storage);
+        return;
+    }
+
+    // `--theme=<name>` renders the resume in another theme than its own, wherever it is given
+    let theme = env::args().find_map(|arg| arg.strip_prefix("--theme=").map(str::to_string));
+    let args = env::args()
+        .filter(|arg| !arg.starts_with("--theme="))
+        .collect::<Vec<String>>();
+
+    let resume_path = args.get(1).expect("No resume path provided");
     let resume = fs::read_to_string(resume_path).unwrap();
-    let resume_data = resume_data::ResumeData::from_json(&resume);
+    let mut resume_data = resume_data::ResumeData::from_json(&resume);
+    if theme.is_some() {
+        resume_data.theme = theme;
+    }
 
-    let results_path = env::args().nth(2).expect("No results path provided");
+    let results_path = args.get(2).expect("No results path provided");
 
-    let debug = if let Some(is_debug) = env::args().nth(3) {
+    let debug = if let Some(is_debug) = args.get(3) {
         is_debug == "--debug"
     } else {
         false
     };
 
-    let local_storage = if let Some(dir) = env::args().nth(4) {
+    let local_storage = if let Some(dir) = args.get(4) {
         local_storage::LocalStorage::custom_dir(dir.as_str())
     } else {
         local_storage::LocalStorage::new()
     };
+    local_storage.initiate_local_storage();
 
-    PngLayout::render_and_save(
+    let diagnostics = match PngLayout::render_and_save(
         local_storage,
         resume_data,
         Path::new(results_path.as_str()),
         debug,
-    )
-    .unwrap();
+    ) {
+        Ok(diagnostics) => diagnostics,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+    };
+    for diagnostic in diagnostics {
+        eprintln!("warning: {}", diagnostic);
+    }
+}
+
+fn list_fonts(local_storage: &local_storage::LocalStorage) {
+    let search_paths = FontRegistry::search_paths(local_storage, &[]);
+    let font_registry = FontRegistry::new(search_paths);
+
+    println!("Search paths:");
+    for path in font_registry.search_paths.iter() {
+        println!("    {}", path.display());
+    }
+
+    for family in font_registry.families() {
+        println!("{}", family);
+        for face in font_registry.faces().iter().filter(|f| f.family == family) {
+            let weight = match face.weight_range {
+                Some((min, max)) => format!("{}-{}", min, max),
+                None => face.weight.to_string(),
+            };
+            let style = if face.italic { "Italic" } else { "Normal" };
+            println!("    {} {} {}", weight, style, face.path.display());
+        }
+    }
 }
@@ -0,0 +1,196 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// An sRGB color with an alpha channel. Colors are written as hex codes (`#1f6feb`,
+/// `#fff`, `#1f6feb80`), as `rgb(31, 111, 235)` or `rgba(31, 111, 235, 0.5)`, or by
+/// their CSS name.
+#[derive(SerializeDisplay, DeserializeFromStr, Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Default for Color {
+    fn default() -> Color {
+        Color::BLACK
+    }
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    // The basic CSS colors, and a few common extended ones
+    const NAMES: [(&'static str, Color); 30] = [
+        ("black", Color::rgb(0, 0, 0)),
+        ("silver", Color::rgb(192, 192, 192)),
+        ("gray", Color::rgb(128, 128, 128)),
+        ("grey", Color::rgb(128, 128, 128)),
+        ("white", Color::rgb(255, 255, 255)),
+        ("maroon", Color::rgb(128, 0, 0)),
+        ("red", Color::rgb(255, 0, 0)),
+        ("purple", Color::rgb(128, 0, 128)),
+        ("fuchsia", Color::rgb(255, 0, 255)),
+        ("magenta", Color::rgb(255, 0, 255)),
+        ("green", Color::rgb(0, 128, 0)),
+        ("lime", Color::rgb(0, 255, 0)),
+        ("olive", Color::rgb(128, 128, 0)),
+        ("yellow", Color::rgb(255, 255, 0)),
+        ("navy", Color::rgb(0, 0, 128)),
+        ("blue", Color::rgb(0, 0, 255)),
+        ("teal", Color::rgb(0, 128, 128)),
+        ("aqua", Color::rgb(0, 255, 255)),
+        ("cyan", Color::rgb(0, 255, 255)),
+        ("orange", Color::rgb(255, 165, 0)),
+        ("darkgray", Color::rgb(169, 169, 169)),
+        ("darkgrey", Color::rgb(169, 169, 169)),
+        ("lightgray", Color::rgb(211, 211, 211)),
+        ("lightgrey", Color::rgb(211, 211, 211)),
+        ("dimgray", Color::rgb(105, 105, 105)),
+        ("darkblue", Color::rgb(0, 0, 139)),
+        ("steelblue", Color::rgb(70, 130, 180)),
+        ("darkred", Color::rgb(139, 0, 0)),
+        ("darkgreen", Color::rgb(0, 100, 0)),
+        ("transparent", Color::rgba(0, 0, 0, 0)),
+    ];
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// Red, green and blue as fractions, the way PDF color operators take them. The PDF
+    /// backend does not use transparency, so translucent colors are blended with the
+    /// white of the page.
+    pub fn to_fractions(self) -> (f32, f32, f32) {
+        let channel = |c: u8| 1.0 - self.alpha() * (1.0 - c as f32 / 255.0);
+        (channel(self.r), channel(self.g), channel(self.b))
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.a as f32 / 255.0
+    }
+
+    fn from_hex(hex: &str) -> Option<Color> {
+        if !hex.is_ascii() {
+            return None;
+        }
+        // Short forms repeat every digit, `#fa0` is `#ffaa00`
+        let hex = match hex.len() {
+            3 | 4 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+            6 | 8 => hex.to_string(),
+            _ => return None,
+        };
+        let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+
+        Some(Color {
+            r: channel(0)?,
+            g: channel(1)?,
+            b: channel(2)?,
+            a: if hex.len() == 8 { channel(3)? } else { 255 },
+        })
+    }
+
+    fn from_function(arguments: &str) -> Option<Color> {
+        let arguments = arguments.split(',').map(str::trim).collect::<Vec<&str>>();
+        // Channels are 0-255 or percentages, alpha is 0-1 or a percentage
+        let channel = |s: &str, max: f32| -> Option<u8> {
+            let value = match s.strip_suffix('%') {
+                Some(percentage) => percentage.trim().parse::<f32>().ok()? / 100.0,
+                None => s.parse::<f32>().ok()? / max,
+            };
+            (0.0..=1.0)
+                .contains(&value)
+                .then(|| (value * 255.0).round() as u8)
+        };
+
+        match arguments.as_slice() {
+            [r, g, b] => Some(Color::rgb(
+                channel(r, 255.0)?,
+                channel(g, 255.0)?,
+                channel(b, 255.0)?,
+            )),
+            [r, g, b, a] => Some(Color::rgba(
+                channel(r, 255.0)?,
+                channel(g, 255.0)?,
+                channel(b, 255.0)?,
+                channel(a, 1.0)?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Color, Self::Err> {
+        let color = s.trim().to_lowercase();
+        let parsed = if let Some(hex) = color.strip_prefix('#') {
+            Color::from_hex(hex)
+        } else if let Some(arguments) = color
+            .strip_prefix("rgba(")
+            .or_else(|| color.strip_prefix("rgb("))
+        {
+            arguments.strip_suffix(')').and_then(Color::from_function)
+        } else {
+            Color::NAMES
+                .iter()
+                .find(|(name, _)| *name == color)
+                .map(|(_, color)| *color)
+        };
+
+        parsed.ok_or_else(|| format!("invalid color: {}", s))
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let colors: Vec<Color> = serde_json::from_str(
+            r##"["#1f6feb", "#FA0", "#1f6feb80", "rgb(31, 111, 235)", "rgba(0, 0, 0, 0.5)",
+                "rgb(100%, 0%, 50%)", "SteelBlue"]"##,
+        )
+        .unwrap();
+        assert_eq!(
+            colors,
+            vec![
+                Color::rgb(31, 111, 235),
+                Color::rgb(255, 170, 0),
+                Color::rgba(31, 111, 235, 128),
+                Color::rgb(31, 111, 235),
+                Color::rgba(0, 0, 0, 128),
+                Color::rgb(255, 0, 128),
+                Color::rgb(70, 130, 180),
+            ]
+        );
+
+        assert!("#12345".parse::<Color>().is_err());
+        assert!("rgb(256, 0, 0)".parse::<Color>().is_err());
+        assert!("ultramarine".parse::<Color>().is_err());
+
+        assert_eq!(
+            serde_json::to_string(&Color::rgba(31, 111, 235, 128)).unwrap(),
+            r##""#1f6feb80""##
+        );
+    }
+}
@@ -3,11 +3,19 @@ use std::{collections::HashMap, fmt::Display};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    alignment::Alignment,
+    alignment::{Alignment, VerticalAlignment},
+    color::Color,
+    diagnostic::Diagnostic,
+    direction::Direction,
     font::{Font, FontDict},
+    height::Height,
+    hyphenation::Hyphenation,
     layout::SectionLayout,
     margin::Margin,
+    overflow::Overflow,
     resume_data::ItemContent,
+    shape::{Border, Shape},
+    style::{Style, Styles},
     width::Width,
 };
 
@@ -19,12 +27,31 @@ pub struct Container {
     #[serde(default = "Uuid::new_v4")]
     pub uid: uuid::Uuid,
     pub elements: Vec<SectionLayout>,
-    #[serde(default = "Margin::default")]
-    pub margin: Margin,
-    #[serde(default = "Alignment::default")]
-    pub alignment: Alignment,
+    // Margin and alignment are left unset when the container takes them from its style
+    #[serde(default = "Option::default")]
+    pub margin: Option<Margin>,
+    #[serde(default = "Option::default")]
+    pub alignment: Option<Alignment>,
     #[serde(default = "Width::default")]
     pub width: Width,
+    #[serde(default = "Height::default")]
+    pub height: Height,
+    #[serde(default = "VerticalAlignment::default")]
+    pub vertical_alignment: VerticalAlignment,
+    // Space between consecutive elements, vertical in stacks and horizontal in rows
+    #[serde(default = "f32::default")]
+    pub gap: f32,
+    // Fill of the box of the container, drawn behind its elements
+    #[serde(default = "Option::default")]
+    pub background: Option<Color>,
+    #[serde(default = "Option::default")]
+    pub border: Option<Border>,
+    // What happens to the elements of a frozen row when they are too wide for it
+    #[serde(default = "Overflow::default")]
+    pub overflow: Overflow,
+    // Name of the style of the layout schema, its font and color cascade to the elements
+    #[serde(default = "Option::default")]
+    pub style: Option<String>,
 }
 
 impl Display for Container {
@@ -41,9 +68,16 @@ impl Container {
         Container {
             uid: Uuid::new_v4(),
             elements: vec![],
-            margin: Margin::default(),
-            alignment: Alignment::default(),
+            margin: None,
+            alignment: None,
             width: Width::default(),
+            height: Height::default(),
+            vertical_alignment: VerticalAlignment::default(),
+            gap: 0.0,
+            background: None,
+            border: None,
+            overflow: Overflow::default(),
+            style: None,
         }
     }
 
@@ -51,6 +85,14 @@ impl Container {
         self.elements.iter().flat_map(|e| e.fonts()).collect()
     }
 
+    pub fn margin(&self) -> Margin {
+        self.margin.unwrap_or_default()
+    }
+
+    pub fn alignment(&self) -> Alignment {
+        self.alignment.unwrap_or_default()
+    }
+
     pub fn with_elements(&self, elements: Vec<SectionLayout>) -> Container {
         Container {
             uid: self.uid,
@@ -58,15 +100,29 @@ impl Container {
             margin: self.margin,
             alignment: self.alignment,
             width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
     pub fn with_margin(&self, margin: Margin) -> Container {
         Container {
             uid: self.uid,
             elements: self.elements.clone(),
-            margin,
+            margin: Some(margin),
             alignment: self.alignment,
             width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
@@ -75,8 +131,15 @@ impl Container {
             uid: self.uid,
             elements: self.elements.clone(),
             margin: self.margin,
-            alignment,
+            alignment: Some(alignment),
             width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
@@ -87,6 +150,132 @@ impl Container {
             margin: self.margin,
             alignment: self.alignment,
             width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
+        }
+    }
+
+    pub fn with_height(&self, height: Height) -> Container {
+        Container {
+            uid: self.uid,
+            elements: self.elements.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
+        }
+    }
+
+    pub fn with_background(&self, background: Option<Color>) -> Container {
+        Container {
+            uid: self.uid,
+            elements: self.elements.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
+        }
+    }
+
+    pub fn with_border(&self, border: Option<Border>) -> Container {
+        Container {
+            uid: self.uid,
+            elements: self.elements.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border,
+            overflow: self.overflow,
+            style: self.style.clone(),
+        }
+    }
+
+    // Elements are laid out inside the border
+    pub fn inset(&self) -> f32 {
+        self.border.map_or(0.0, |border| border.width)
+    }
+
+    pub fn inner_width(&self) -> f32 {
+        f32::max(self.width.get_fixed_unchecked() - 2.0 * self.inset(), 0.0)
+    }
+
+    // The shape drawn behind the elements, if the container has a background or a border
+    pub fn shape(&self) -> Option<Shape> {
+        match (self.background, self.border) {
+            (None, None) => None,
+            (fill, border) => Some(Shape::Rectangle { fill, border }),
+        }
+    }
+
+    pub fn with_direction(&self, direction: Direction) -> Container {
+        self.with_elements(
+            self.elements
+                .iter()
+                .map(|e| e.with_direction(direction))
+                .collect(),
+        )
+    }
+
+    pub fn inherit_hyphenation(&self, hyphenation: &Option<Hyphenation>) -> Container {
+        self.with_elements(
+            self.elements
+                .iter()
+                .map(|e| e.inherit_hyphenation(hyphenation))
+                .collect(),
+        )
+    }
+
+    pub fn inherit_font_fallbacks(&self, fallbacks: &[String]) -> Container {
+        self.with_elements(
+            self.elements
+                .iter()
+                .map(|e| e.inherit_font_fallbacks(fallbacks))
+                .collect(),
+        )
+    }
+
+    // Takes the margin and the alignment the container leaves unset from its style, and
+    // resolves the styles of the elements with the font and color cascading down
+    pub fn resolve_styles(&self, styles: &Styles, inherited: &Style) -> Container {
+        let style = Style::find(&self.style, styles).over(inherited);
+        Container {
+            uid: self.uid,
+            elements: self
+                .elements
+                .iter()
+                .map(|e| e.resolve_styles(styles, &style.inherited()))
+                .collect(),
+            margin: self.margin.or(style.margin),
+            alignment: self.alignment.or(style.alignment),
+            width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
@@ -101,24 +290,36 @@ impl Container {
             margin: self.margin,
             alignment: self.alignment,
             width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
     pub fn bound_width(&self, width: f32) -> Container {
-        let bound = match self.width {
-            Width::Absolute(w) => f32::min(w, width),
-            Width::Percentage(_) => unreachable!(
-                "SectionLayout::bound_width: Cannot bounded width for non-unitized widths!"
-            ),
-            Width::Fill => width,
-        };
+        let bound = self.width.bound(width).get_fixed_unchecked();
 
         Container {
             uid: self.uid,
-            elements: self.elements.iter().map(|e| e.bound_width(bound)).collect(),
+            elements: self
+                .elements
+                .iter()
+                .map(|e| e.bound_width(f32::max(bound - 2.0 * self.inset(), 0.0)))
+                .collect(),
             margin: self.margin,
             alignment: self.alignment,
             width: Width::Absolute(bound),
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
@@ -128,7 +329,15 @@ impl Container {
             elements: self.elements.iter().map(|e| e.scale_width(w)).collect(),
             margin: self.margin,
             alignment: self.alignment,
-            width: self.width.scale(w),
+            // Containers have no font of their own, so ems are relative to the default font size
+            width: self.width.scale(w, Font::default_size()),
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
@@ -139,25 +348,44 @@ impl Container {
             margin: self.margin,
             alignment: self.alignment,
             width: self.width,
+            height: self.height,
+            vertical_alignment: self.vertical_alignment,
+            gap: self.gap,
+            background: self.background,
+            border: self.border,
+            overflow: self.overflow,
+            style: self.style.clone(),
         }
     }
 
-    pub fn break_lines(&self, font_dict: &FontDict) -> Vec<Container> {
+    pub fn break_lines(
+        &self,
+        font_dict: &FontDict,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Vec<Container> {
         let mut lines: Vec<Container> = vec![];
         let mut current_line: Vec<SectionLayout> = vec![];
         let mut current_width = 0.0;
         let elements: Vec<SectionLayout> = self
             .elements
             .iter()
-            .map(|e| e.break_lines(font_dict))
+            .map(|e| e.break_lines(font_dict, diagnostics))
             .collect();
 
         for element in elements {
             let element_width = element.width().get_fixed_unchecked();
-            if current_width + element_width > self.width.get_fixed().unwrap() {
+            let gap = if current_line.is_empty() {
+                0.0
+            } else {
+                self.gap
+            };
+            if !current_line.is_empty() && current_width + gap + element_width > self.inner_width()
+            {
                 lines.push(self.with_elements(current_line));
                 current_line = vec![];
                 current_width = 0.0;
+            } else {
+                current_width += gap;
             }
             current_line.push(element.clone());
             current_width += element_width;
@@ -167,13 +395,39 @@ impl Container {
             lines.push(self.with_elements(current_line));
         }
 
+        // The height and the vertical alignment of a row that wraps apply to the stack of its
+        // lines, which is built from the row, not to each line
+        if lines.len() > 1 {
+            lines = lines
+                .into_iter()
+                .map(|line| line.with_height(Height::Auto))
+                .collect();
+        }
+
+        // The lines are stacked in a container that keeps the background and the border
+        // of the row, and are as wide as the inside of the border
         lines
+            .into_iter()
+            .map(|line| {
+                line.with_background(None)
+                    .with_border(None)
+                    .with_width(Width::Absolute(self.inner_width()))
+            })
+            .collect()
+    }
+
+    // Total width of the elements of a row, including the gaps between them
+    // Elements fitted to the width of the row may be off by rounding errors
+    pub fn overflows(&self) -> bool {
+        self.elements_width() > self.inner_width() + 0.01
     }
 
     pub fn elements_width(&self) -> f32 {
+        let gaps = self.elements.len().saturating_sub(1) as f32 * self.gap;
         self.elements
             .iter()
             .map(|e| e.width().get_fixed_unchecked())
-            .sum()
+            .sum::<f32>()
+            + gaps
     }
 }
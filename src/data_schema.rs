@@ -1,9 +1,12 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 
-use crate::{layout_schema::Named, resume_data::ItemContent};
+use crate::{
+    layout_schema::Named,
+    resume_data::{ItemContent, ItemName},
+};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(untagged)]
@@ -11,26 +14,44 @@ pub enum DocumentDataType {
     Date,
     String,
     MarkdownString,
+    // Path of an image file, relative to the storage directory
+    Image,
+    // A number, optionally in an inclusive range, written as `Number(1..5)`, `Number(0..)`
+    Number { min: Option<f32>, max: Option<f32> },
     Type(String),
     List(Box<DocumentDataType>),
     Types(Vec<DocumentDataType>),
 }
 
 impl DocumentDataType {
-    #[allow(dead_code)]
-    fn validate(data_type: &DocumentDataType, value: &ItemContent) -> bool {
+    pub fn validate(data_type: &DocumentDataType, value: &ItemContent) -> bool {
         match value {
-            ItemContent::None => unreachable!(),
-            ItemContent::String(_) => match data_type {
-                DocumentDataType::Date => todo!(),
+            // Like missing values, empty entries of a list are valid
+            ItemContent::None => true,
+            ItemContent::String(s) => match data_type {
+                // Dates are not parsed yet
+                DocumentDataType::Date => true,
                 DocumentDataType::String => true,
                 DocumentDataType::MarkdownString => true,
+                DocumentDataType::Image => true,
+                DocumentDataType::Number { min, max } => s
+                    .trim()
+                    .parse::<f32>()
+                    .is_ok_and(|n| DocumentDataType::in_range(n, *min, *max)),
                 DocumentDataType::Type(_) => true,
                 DocumentDataType::List(_) => false,
                 DocumentDataType::Types(t) => {
                     t.iter().any(|t| DocumentDataType::validate(t, value))
                 }
             },
+            ItemContent::Number(n) => match data_type {
+                DocumentDataType::Number { min, max } => DocumentDataType::in_range(*n, *min, *max),
+                DocumentDataType::String | DocumentDataType::Type(_) => true,
+                DocumentDataType::Types(t) => {
+                    t.iter().any(|t| DocumentDataType::validate(t, value))
+                }
+                _ => false,
+            },
             ItemContent::List(items) => {
                 if let DocumentDataType::List(t) = data_type {
                     items.iter().all(|i| DocumentDataType::validate(t, i))
@@ -41,6 +62,29 @@ impl DocumentDataType {
             ItemContent::Url { url: _, text: _ } => true,
         }
     }
+
+    fn in_range(n: f32, min: Option<f32>, max: Option<f32>) -> bool {
+        !min.is_some_and(|min| n < min) && !max.is_some_and(|max| n > max)
+    }
+
+    // Parses the range of `Number(min..max)`, either of the bounds can be left out
+    fn number_range(range: &str) -> Result<DocumentDataType, String> {
+        let (min, max) = range
+            .split_once("..")
+            .ok_or_else(|| format!("invalid range: {}", range))?;
+        let bound = |b: &str| match b.trim() {
+            "" => Ok(None),
+            b => b
+                .parse::<f32>()
+                .map(Some)
+                .map_err(|_| format!("invalid range: {}", range)),
+        };
+
+        Ok(DocumentDataType::Number {
+            min: bound(min)?,
+            max: bound(max)?,
+        })
+    }
 }
 
 impl std::fmt::Display for DocumentDataType {
@@ -59,6 +103,15 @@ impl std::fmt::Display for DocumentDataType {
             DocumentDataType::Date => write!(f, "Date"),
             DocumentDataType::String => write!(f, "String"),
             DocumentDataType::MarkdownString => write!(f, "MarkdownString"),
+            DocumentDataType::Image => write!(f, "Image"),
+            DocumentDataType::Number {
+                min: None,
+                max: None,
+            } => write!(f, "Number"),
+            DocumentDataType::Number { min, max } => {
+                let bound = |b: &Option<f32>| b.map(|b| b.to_string()).unwrap_or_default();
+                write!(f, "Number({}..{})", bound(min), bound(max))
+            }
         }
     }
 }
@@ -70,6 +123,11 @@ impl FromStr for DocumentDataType {
             "Date" => DocumentDataType::Date,
             "String" => DocumentDataType::String,
             "MarkdownString" => DocumentDataType::MarkdownString,
+            "Image" => DocumentDataType::Image,
+            "Number" => DocumentDataType::Number {
+                min: None,
+                max: None,
+            },
             _ => {
                 if s.contains('|') {
                     let types: Vec<&str> = s.split('|').collect();
@@ -78,6 +136,10 @@ impl FromStr for DocumentDataType {
                         data_types.push(DocumentDataType::from_str(t.trim())?);
                     }
                     DocumentDataType::Types(data_types)
+                } else if let Some(range) =
+                    s.strip_prefix("Number(").and_then(|s| s.strip_suffix(')'))
+                {
+                    DocumentDataType::number_range(range)?
                 } else if s.starts_with("List") {
                     let list_type = s.get(5..(s.len() - 1)).unwrap();
                     DocumentDataType::List(Box::new(DocumentDataType::from_str(list_type)?))
@@ -117,6 +179,26 @@ impl DataSchema {
         let schema: Vec<DataSchema> = serde_json::from_str(json).unwrap();
         schema
     }
+
+    /// Checks the values of a header or an item against the types of their fields, and
+    /// describes the values that do not match. Missing values are not reported.
+    pub fn validate(fields: &[Field], values: &HashMap<ItemName, ItemContent>) -> Vec<String> {
+        fields
+            .iter()
+            .filter_map(|field| {
+                let value = values.get(&field.name)?;
+                if matches!(value, ItemContent::None)
+                    || DocumentDataType::validate(&field.data_type, value)
+                {
+                    return None;
+                }
+                Some(format!(
+                    "{} is not a valid {}: {}",
+                    field.name, field.data_type, value
+                ))
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -173,7 +255,7 @@ mod tests {
         ]
         "#;
         let schema = DataSchema::from_json(json);
-        assert_eq!(schema[0].name, "Education");
+        assert_eq!(schema[0].schema_name, "Education");
         assert_eq!(schema[0].item_schema[0].name, "School");
         assert_eq!(schema[0].item_schema[0].data_type, DocumentDataType::String);
         assert_eq!(schema[0].item_schema[1].name, "Degree");
@@ -190,4 +272,52 @@ mod tests {
             DocumentDataType::Type("URLString".to_string())
         );
     }
+
+    #[test]
+    fn test_validate() {
+        let level: DocumentDataType = "Number(1..5)".parse().unwrap();
+        assert_eq!(
+            level,
+            DocumentDataType::Number {
+                min: Some(1.0),
+                max: Some(5.0)
+            }
+        );
+        assert_eq!(level.to_string(), "Number(1..5)");
+        assert_eq!(
+            "Number(0..)".parse::<DocumentDataType>().unwrap(),
+            DocumentDataType::Number {
+                min: Some(0.0),
+                max: None
+            }
+        );
+        assert!("Number(one..5)".parse::<DocumentDataType>().is_err());
+
+        let fields: Vec<Field> = serde_json::from_str(
+            r#"[
+                { "name": "Skill", "type": "String" },
+                { "name": "Level", "type": "Number(1..5)" },
+                { "name": "Years", "type": "Number" },
+                { "name": "Tags", "type": "List(String)" }
+            ]"#,
+        )
+        .unwrap();
+        let item: HashMap<ItemName, ItemContent> = serde_json::from_str(
+            r#"{ "Skill": "Rust", "Level": 4.5, "Years": "3", "Tags": ["Systems", null] }"#,
+        )
+        .unwrap();
+        assert!(DataSchema::validate(&fields, &item).is_empty());
+
+        let item: HashMap<ItemName, ItemContent> =
+            serde_json::from_str(r#"{ "Skill": 3, "Level": 7, "Years": "three" }"#).unwrap();
+        let mut errors = DataSchema::validate(&fields, &item);
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "Level is not a valid Number(1..5): 7",
+                "Years is not a valid Number: three"
+            ]
+        );
+    }
 }
@@ -0,0 +1,38 @@
+use std::fmt::Display;
+
+/// A problem met while laying out a section that the layout recovered from, such as text
+/// too wide for its box. Layouts report the field it is about, the section is added by the
+/// document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub section: Option<String>,
+    pub field: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(field: String, message: String) -> Diagnostic {
+        Diagnostic {
+            section: None,
+            field,
+            message,
+        }
+    }
+
+    pub fn in_section(&self, section: &str) -> Diagnostic {
+        Diagnostic {
+            section: Some(section.to_string()),
+            field: self.field.clone(),
+            message: self.message.clone(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "{}, {}: {}", section, self.field, self.message),
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
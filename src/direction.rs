@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use unicode_bidi::{BidiInfo, Level};
+
+/// Base direction of a document, a section or a paragraph. Right-to-left layouts
+/// are mirrored, rows start from the right and `Alignment::Left` aligns to the start
+/// of the line, which is its right edge.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    fn level(&self) -> Level {
+        match self {
+            Direction::Ltr => Level::ltr(),
+            Direction::Rtl => Level::rtl(),
+        }
+    }
+
+    /// Resolves the embedding levels of `text` as a paragraph with this base direction,
+    /// and splits it into directional runs given in visual order, from left to right.
+    pub fn visual_runs(&self, text: &str) -> Vec<(String, Direction)> {
+        let bidi_info = BidiInfo::new(text, Some(self.level()));
+
+        bidi_info
+            .paragraphs
+            .iter()
+            .flat_map(|paragraph| {
+                let (levels, runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+                runs.into_iter()
+                    .map(|run| {
+                        let direction = if levels[run.start].is_rtl() {
+                            Direction::Rtl
+                        } else {
+                            Direction::Ltr
+                        };
+                        (text[run].to_string(), direction)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visual_runs() {
+        assert_eq!(
+            Direction::Ltr.visual_runs("hello world"),
+            vec![("hello world".to_string(), Direction::Ltr)]
+        );
+
+        // An English name inside a Hebrew sentence keeps its own order,
+        // while the sentence around it is read from the right
+        let runs = Direction::Rtl.visual_runs("עבדתי ב Acme Corp שנתיים");
+        assert_eq!(
+            runs,
+            vec![
+                (" שנתיים".to_string(), Direction::Rtl),
+                ("Acme Corp".to_string(), Direction::Ltr),
+                ("עבדתי ב ".to_string(), Direction::Rtl),
+            ]
+        );
+    }
+}
@@ -4,10 +4,21 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     alignment::Alignment,
-    font::{Font, FontDict},
+    color::Color,
+    diagnostic::Diagnostic,
+    direction::Direction,
+    font::{Font, FontDict, PartialFont},
+    hyphenation::Hyphenation,
+    image_element::ImageElement,
     margin::Margin,
+    overflow::Overflow,
+    shape::Shape,
+    shaping::{PositionedGlyph, ShapedText},
+    style::Style,
+    text_transform::TextTransform,
     width::Width,
 };
+use unicode_linebreak::{linebreaks, BreakOpportunity};
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -16,22 +27,64 @@ pub struct Element {
     #[serde(default = "Uuid::new_v4")]
     pub uid: Uuid,
     pub item: String,
-    #[serde(default = "Margin::default")]
-    pub margin: Margin,
-    #[serde(default = "Alignment::default")]
-    pub alignment: Alignment,
+    // Margin, alignment and font are left unset when the element takes them from its style
+    #[serde(default = "Option::default")]
+    pub margin: Option<Margin>,
+    #[serde(default = "Option::default")]
+    pub alignment: Option<Alignment>,
     #[serde(default = "Width::default")]
     pub width: Width,
     #[serde(default = "Width::default")]
     pub text_width: Width,
-    #[serde(default = "Font::default")]
-    pub font: Font,
+    #[serde(default = "PartialFont::default")]
+    pub font: PartialFont,
+    // Wrapped lines are hyphenated when set, falls back to the setting of the layout schema
+    #[serde(default = "Option::default")]
+    pub hyphenation: Option<Hyphenation>,
+    // Color of the text, falls back to the color of the font
+    #[serde(default = "Option::default")]
+    pub color: Option<Color>,
+    // Highlight drawn behind the text
+    #[serde(default = "Option::default")]
+    pub background: Option<Color>,
+    // Graphics drawn in the box of the element, for the decorations of containers and rules
+    #[serde(skip)]
+    #[serde(default = "Option::default")]
+    pub shape: Option<Shape>,
+    // Image drawn in the box of the element, for image nodes
+    #[serde(skip)]
+    #[serde(default = "Option::default")]
+    pub image: Option<ImageElement>,
     #[serde(skip)]
     #[serde(default = "bool::default")]
     pub is_fill: bool,
     #[serde(skip)]
     #[serde(default = "Option::default")]
     pub url: Option<String>,
+    // Base direction of the paragraph, set from the document or the section
+    #[serde(skip)]
+    #[serde(default = "Direction::default")]
+    pub direction: Direction,
+    // What happens to text too wide for its box, it wraps by default
+    #[serde(default = "Overflow::default")]
+    pub overflow: Overflow,
+    // Field of the section the text was read from, for diagnostics
+    #[serde(skip)]
+    #[serde(default = "Option::default")]
+    pub field: Option<String>,
+    // Lines past the maximum are hidden, the last shown one ends with an ellipsis if set
+    #[serde(default = "Option::default")]
+    pub max_lines: Option<usize>,
+    #[serde(default = "Element::default_ellipsis")]
+    pub ellipsis: bool,
+    #[serde(default = "TextTransform::default")]
+    pub text_transform: TextTransform,
+    // Space added after every letter, in points
+    #[serde(default = "f32::default")]
+    pub letter_spacing: f32,
+    // Name of the style of the layout schema the element takes its defaults from
+    #[serde(default = "Option::default")]
+    pub style: Option<String>,
 }
 
 impl Display for Element {
@@ -51,19 +104,69 @@ impl Default for Element {
     fn default() -> Element {
         Element {
             item: String::new(),
-            margin: Margin::default(),
-            alignment: Alignment::default(),
+            margin: None,
+            alignment: None,
             width: Width::default(),
             text_width: Width::default(),
-            font: Font::default(),
+            font: PartialFont::default(),
+            hyphenation: None,
+            color: None,
+            background: None,
+            shape: None,
+            image: None,
             is_fill: false,
             url: None,
+            direction: Direction::default(),
+            overflow: Overflow::default(),
+            field: None,
+            max_lines: None,
+            ellipsis: Element::default_ellipsis(),
+            text_transform: TextTransform::default(),
+            letter_spacing: 0.0,
+            style: None,
             uid: Uuid::new_v4(),
         }
     }
 }
 
 impl Element {
+    pub fn default_ellipsis() -> bool {
+        true
+    }
+
+    // An element without text that only draws a shape, for the boxes of containers and rules
+    pub fn shape_box(shape: Shape) -> Element {
+        Element {
+            shape: Some(shape),
+            ..Element::default()
+        }
+    }
+
+    // An element without text that only draws an image
+    pub fn image_box(image: ImageElement) -> Element {
+        Element {
+            image: Some(image),
+            ..Element::default()
+        }
+    }
+
+    pub fn margin(&self) -> Margin {
+        self.margin.unwrap_or_default()
+    }
+
+    pub fn alignment(&self) -> Alignment {
+        self.alignment.unwrap_or_default()
+    }
+
+    pub fn font(&self) -> Font {
+        self.font.resolve()
+    }
+
+    // The color of the element falls back to the color of its font
+    pub fn text_color(&self) -> Color {
+        self.color.unwrap_or(self.font().color)
+    }
+
     pub fn with_item(&self, item: String) -> Element {
         Element {
             item,
@@ -72,8 +175,21 @@ impl Element {
             width: self.width,
             text_width: self.text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
@@ -81,13 +197,26 @@ impl Element {
     pub fn with_margin(&self, margin: Margin) -> Element {
         Element {
             item: self.item.clone(),
-            margin,
+            margin: Some(margin),
             alignment: self.alignment,
             width: self.width,
             text_width: self.text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
@@ -96,12 +225,25 @@ impl Element {
         Element {
             item: self.item.clone(),
             margin: self.margin,
-            alignment,
+            alignment: Some(alignment),
             width: self.width,
             text_width: self.text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
@@ -114,8 +256,21 @@ impl Element {
             width,
             text_width: self.text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
@@ -128,8 +283,21 @@ impl Element {
             width: self.width,
             text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
@@ -142,8 +310,221 @@ impl Element {
             width: self.width,
             text_width: self.text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: Some(url),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
+            uid: self.uid,
+        }
+    }
+
+    pub fn with_hyphenation(&self, hyphenation: Option<Hyphenation>) -> Element {
+        Element {
+            item: self.item.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            text_width: self.text_width,
+            font: self.font.clone(),
+            hyphenation,
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
+            is_fill: self.is_fill,
+            url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
+            uid: self.uid,
+        }
+    }
+
+    pub fn inherit_hyphenation(&self, hyphenation: &Option<Hyphenation>) -> Element {
+        match self.hyphenation {
+            Some(_) => self.clone(),
+            None => self.with_hyphenation(hyphenation.clone()),
+        }
+    }
+
+    pub fn with_font(&self, font: Font) -> Element {
+        Element {
+            item: self.item.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            text_width: self.text_width,
+            font: PartialFont::from(font),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
+            is_fill: self.is_fill,
+            url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
+            uid: self.uid,
+        }
+    }
+
+    pub fn inherit_font_fallbacks(&self, fallbacks: &[String]) -> Element {
+        self.with_font(self.font().inherit_fallbacks(fallbacks))
+    }
+
+    // Takes the properties the element leaves unset from `style`, the font property by property
+    pub fn with_style(&self, style: &Style) -> Element {
+        Element {
+            item: self.item.clone(),
+            margin: self.margin.or(style.margin),
+            alignment: self.alignment.or(style.alignment),
+            width: self.width,
+            text_width: self.text_width,
+            font: self.font.over(&style.font),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color.or(style.color),
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
+            is_fill: self.is_fill,
+            url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
+            uid: self.uid,
+        }
+    }
+
+    pub fn with_field(&self, field: String) -> Element {
+        Element {
+            item: self.item.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            text_width: self.text_width,
+            font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
+            is_fill: self.is_fill,
+            url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: Some(field),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
+            uid: self.uid,
+        }
+    }
+
+    // How diagnostics refer to the element, by its field or by its text
+    pub fn field_name(&self) -> String {
+        self.field
+            .clone()
+            .unwrap_or_else(|| format!("\"{}\"", self.item))
+    }
+
+    /// Fits the text into a single line of `width` following `overflow`, by making its font
+    /// smaller or by cutting it. Wrapping text is left as it is, lines are broken later.
+    pub fn fit_overflow(&self, width: f32, overflow: Overflow, font_dict: &FontDict) -> Element {
+        let element = match overflow {
+            Overflow::Wrap => return self.clone(),
+            Overflow::Shrink => {
+                let mut element = self.clone();
+                // Text is about as wide as its font is large, a few rounds settle the rest
+                for _ in 0..4 {
+                    let text_width = element.measure(&element.item, font_dict);
+                    if text_width <= width || text_width == 0.0 {
+                        break;
+                    }
+                    let size = element.font().size * width / text_width;
+                    element = element.with_font(element.font().with_size(size));
+                }
+                element
+            }
+            Overflow::Ellipsis => self.with_item(self.cut(&self.item, width, "…", font_dict)),
+            Overflow::Clip => self.with_item(self.cut(&self.item, width, "", font_dict)),
+        };
+
+        element
+            .with_width(Width::Absolute(width))
+            .fill_fonts(font_dict)
+    }
+
+    // The longest beginning of `text` that fits into `width` with `suffix` after it
+    fn cut(&self, text: &str, width: f32, suffix: &str, font_dict: &FontDict) -> String {
+        let cut = |end: usize| format!("{}{}", text[..end].trim_end(), suffix);
+        // Text is only cut between shaping clusters, so marks stay with their letters
+        let ends = self
+            .clusters(text, font_dict)
+            .into_iter()
+            .scan(0, |end, cluster| {
+                *end += cluster.len();
+                Some(*end)
+            });
+        let end = std::iter::once(0)
+            .chain(ends)
+            .take_while(|end| self.measure(&cut(*end), font_dict) <= width)
+            .last()
+            .unwrap_or(0);
+        cut(end)
+    }
+
+    pub fn with_direction(&self, direction: Direction) -> Element {
+        Element {
+            item: self.item.clone(),
+            margin: self.margin,
+            alignment: self.alignment,
+            width: self.width,
+            text_width: self.text_width,
+            font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
+            is_fill: self.is_fill,
+            url: self.url.clone(),
+            direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
@@ -153,86 +534,391 @@ impl Element {
             item: self.item.clone(),
             margin: self.margin,
             alignment: self.alignment,
-            width: self.width.scale(w),
+            width: self.width.scale(w, self.font().size),
             text_width: self.text_width,
             font: self.font.clone(),
+            hyphenation: self.hyphenation.clone(),
+            color: self.color,
+            background: self.background,
+            shape: self.shape,
+            image: self.image.clone(),
             is_fill: self.is_fill,
             url: self.url.clone(),
+            direction: self.direction,
+            overflow: self.overflow,
+            field: self.field.clone(),
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            text_transform: self.text_transform,
+            letter_spacing: self.letter_spacing,
+            style: self.style.clone(),
             uid: self.uid,
         }
     }
 
     pub fn fill_fonts(&self, fonts: &FontDict) -> Element {
-        let text_width_with_font = self.font.get_width(&self.item, fonts);
+        // The text is transformed before it is measured, so that it wraps as it is drawn
+        let item = self.text_transform.apply(&self.item, &self.font(), fonts);
+        let text_width_with_font = self.measure(&item, fonts);
         if self.is_fill {
             Element {
-                item: self.item.clone(),
+                item: item.clone(),
                 margin: self.margin,
                 alignment: self.alignment,
-                width: Width::Absolute(f32::min(
+                width: Width::Absolute(self.width.clamp(f32::min(
                     self.width.get_fixed().unwrap(),
                     text_width_with_font,
-                )),
+                ))),
                 text_width: Width::Absolute(text_width_with_font),
                 font: self.font.clone(),
+                hyphenation: self.hyphenation.clone(),
+                color: self.color,
+                background: self.background,
+                shape: self.shape,
+                image: self.image.clone(),
                 is_fill: self.is_fill,
                 url: self.url.clone(),
+                direction: self.direction,
+                overflow: self.overflow,
+                field: self.field.clone(),
+                max_lines: self.max_lines,
+                ellipsis: self.ellipsis,
+                text_transform: self.text_transform,
+                letter_spacing: self.letter_spacing,
+                style: self.style.clone(),
                 uid: self.uid,
             }
         } else {
             Element {
-                item: self.item.clone(),
+                item: item.clone(),
                 margin: self.margin,
                 alignment: self.alignment,
                 width: self.width,
                 text_width: Width::Absolute(text_width_with_font),
                 font: self.font.clone(),
+                hyphenation: self.hyphenation.clone(),
+                color: self.color,
+                background: self.background,
+                shape: self.shape,
+                image: self.image.clone(),
                 is_fill: self.is_fill,
                 url: self.url.clone(),
+                direction: self.direction,
+                overflow: self.overflow,
+                field: self.field.clone(),
+                max_lines: self.max_lines,
+                ellipsis: self.ellipsis,
+                text_transform: self.text_transform,
+                letter_spacing: self.letter_spacing,
+                style: self.style.clone(),
                 uid: self.uid,
             }
         }
     }
 
-    pub fn break_lines(&self, font_dict: &FontDict) -> Vec<Element> {
-        if self.text_width.get_fixed_unchecked() <= self.width.get_fixed_unchecked() {
-            return vec![self.clone()];
-        }
+    pub fn break_lines(
+        &self,
+        font_dict: &FontDict,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Vec<Element> {
+        let width = self.width.get_fixed_unchecked();
+        let segments = linebreaks(&self.item)
+            .scan(0, |start, (end, opportunity)| {
+                let segment = &self.item[*start..end];
+                *start = end;
+                Some((segment, opportunity == BreakOpportunity::Mandatory))
+            })
+            .collect::<Vec<(&str, bool)>>();
+
+        // The end of the text is always a mandatory break, any other one is a newline
+        let has_newlines = segments
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|(_, mandatory)| *mandatory);
 
-        let mut lines: Vec<Element> = vec![];
+        if self.text_width.get_fixed_unchecked() <= width && !has_newlines {
+            return vec![self.with_last_line_alignment()];
+        }
 
-        // todo: I'm sure this implementation is pretty buggy. Note to future me, fix
-        // this.
-        let words = self.item.split_whitespace().collect::<Vec<&str>>();
+        let mut lines: Vec<String> = vec![];
         let mut line = String::new();
-        for word in words {
-            let candidate_line = line.clone() + " " + word;
-            let candidate_width: f32 = self.font.get_width(&candidate_line, font_dict);
-
-            if candidate_width > self.width.get_fixed_unchecked() {
-                line.pop();
-                let line_width = self.font.get_width(&line, font_dict);
-                lines.push(
-                    self.with_item(line)
-                        .with_text_width(Width::Absolute(line_width)),
-                );
-                line = String::new();
+        let mut line_width = 0.0;
+        let mut consecutive_hyphens = 0;
+
+        // Segments run from one break opportunity to the next, each of them is measured
+        // once and the line width is kept as a running sum.
+        for (segment, mandatory) in segments {
+            let mut segment = segment;
+
+            // A word that overflows the line is hyphenated, its remainder goes to the next line
+            while let Some((head, tail)) = self
+                .hyphenation
+                .as_ref()
+                .filter(|h| consecutive_hyphens < h.max_consecutive_hyphens)
+                .and_then(|h| self.hyphenate(h, segment, width - line_width, font_dict))
+            {
+                line.push_str(&head);
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+                consecutive_hyphens += 1;
+                segment = tail;
             }
 
-            line.push_str(word);
-            line.push(' ');
-        }
+            // Trailing spaces hang past the end of the line, only the visible part has to fit
+            let visible_width = self.shape_text(segment.trim_end(), None, font_dict).width;
+            let advance = self.shape_text(segment, None, font_dict).width;
+
+            if line_width + visible_width > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+                consecutive_hyphens = 0;
+            }
+
+            if visible_width > width {
+                // Emergency break for tokens without break opportunities, such as long urls
+                for cluster in self.clusters(segment, font_dict) {
+                    let cluster_width = self.shape_text(cluster, None, font_dict).width;
+                    if line_width + cluster_width > width && !line.is_empty() {
+                        lines.push(std::mem::take(&mut line));
+                        line_width = 0.0;
+                        consecutive_hyphens = 0;
+                    }
+                    line.push_str(cluster);
+                    line_width += cluster_width;
+                }
+            } else {
+                line.push_str(segment);
+                line_width += advance;
+            }
 
-        line.pop();
-        if !line.is_empty() {
-            let line_width = self.font.get_width(&line, font_dict);
-            lines.push(
-                self.with_item(line)
-                    .with_text_width(Width::Absolute(line_width)),
-            );
+            if mandatory {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+                consecutive_hyphens = 0;
+            }
         }
 
+        let lines = lines
+            .into_iter()
+            .map(|line| line.trim_end().to_string())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<String>>();
+
+        let lines = match self.max_lines {
+            Some(max_lines) if lines.len() > max_lines => {
+                diagnostics.push(Diagnostic::new(
+                    self.field_name(),
+                    format!(
+                        "{} of its {} lines are hidden by max_lines",
+                        lines.len() - max_lines,
+                        lines.len()
+                    ),
+                ));
+                let mut lines = lines;
+                lines.truncate(max_lines);
+                if let Some(last) = lines.last_mut().filter(|_| self.ellipsis) {
+                    *last = self.cut(last, width, "…", font_dict);
+                }
+                lines
+            }
+            _ => lines,
+        };
+        let last = lines.len().saturating_sub(1);
+
         lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let line_width = self.measure(&line, font_dict);
+                let element = self
+                    .with_item(line)
+                    .with_text_width(Width::Absolute(line_width));
+                if index == last {
+                    element.with_last_line_alignment()
+                } else {
+                    element
+                }
+            })
+            .collect()
+    }
+
+    // Splits `text` at the boundaries of its shaping clusters, which keep letters with their
+    // combining marks, and emoji and the joined sequences of complex scripts together
+    fn clusters<'a>(&self, text: &'a str, font_dict: &FontDict) -> Vec<&'a str> {
+        let mut starts = self
+            .shape_text(text, None, font_dict)
+            .glyphs
+            .iter()
+            .map(|g| g.cluster)
+            .chain(std::iter::once(0))
+            .collect::<Vec<usize>>();
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+            .iter()
+            .zip(starts.iter().skip(1).chain(std::iter::once(&text.len())))
+            .map(|(start, end)| &text[*start..*end])
+            .filter(|cluster| !cluster.is_empty())
+            .collect()
+    }
+
+    // Splits an overflowing word at its last syllable boundary that still fits in
+    // `available`, returning the hyphenated head and the rest of the segment.
+    fn hyphenate<'a>(
+        &self,
+        hyphenation: &Hyphenation,
+        segment: &'a str,
+        available: f32,
+        font_dict: &FontDict,
+    ) -> Option<(String, &'a str)> {
+        let word = segment.trim_end();
+        if self.shape_text(word, None, font_dict).width <= available {
+            return None;
+        }
+
+        let syllables = hyphenation.syllables(word);
+        let mut split = None;
+        let mut head_len = 0;
+        for syllable in &syllables[..syllables.len().saturating_sub(1)] {
+            head_len += syllable.len();
+            let head = format!("{}-", &word[..head_len]);
+            if self.shape_text(&head, None, font_dict).width > available {
+                break;
+            }
+            split = Some((head, &segment[head_len..]));
+        }
+
+        split
+    }
+
+    // The last line of a justified paragraph is not stretched, it is left aligned instead
+    fn with_last_line_alignment(&self) -> Element {
+        match self.alignment() {
+            Alignment::Justified => self.with_alignment(Alignment::Left),
+            _ => self.clone(),
+        }
+    }
+
+    // Shapes text in the font of the element, with its letter spacing and the features
+    // of its text transform
+    fn shape_text(
+        &self,
+        text: &str,
+        direction: Option<Direction>,
+        font_dict: &FontDict,
+    ) -> ShapedText {
+        self.font()
+            .shape_runs(text, direction, &self.text_transform.features(), font_dict)
+            .with_letter_spacing(self.letter_spacing)
+    }
+
+    /// Width of `text` as it is drawn by the element.
+    pub fn measure(&self, text: &str, font_dict: &FontDict) -> f32 {
+        let text = text.trim();
+
+        if text.is_empty() {
+            return 0.0;
+        }
+
+        self.shape_text(text, None, font_dict).width
+    }
+
+    /// Shapes the element for drawing inside a box of `width`. Glyphs are positioned
+    /// relative to the start of the baseline.
+    pub fn shape(&self, width: f32, font_dict: &FontDict) -> Vec<PositionedGlyph> {
+        self.text_runs(width, font_dict)
+            .into_iter()
+            .flat_map(|(offset, text, direction)| {
+                self.shape_text(&text, Some(direction), font_dict)
+                    .move_x_by(offset)
+                    .glyphs
+            })
+            .collect()
+    }
+
+    /// Shapes the element like `shape`, with the glyphs grouped by the shaping cluster they
+    /// come from and the text of that cluster. A ligature is a single cluster for all the
+    /// characters it replaces.
+    pub fn shape_clusters(
+        &self,
+        width: f32,
+        font_dict: &FontDict,
+    ) -> Vec<(String, Vec<PositionedGlyph>)> {
+        let mut clusters = Vec::new();
+        for (offset, text, direction) in self.text_runs(width, font_dict) {
+            let glyphs = self
+                .shape_text(&text, Some(direction), font_dict)
+                .move_x_by(offset)
+                .glyphs;
+            let mut starts = glyphs.iter().map(|g| g.cluster).collect::<Vec<usize>>();
+            starts.sort_unstable();
+            starts.dedup();
+            for cluster in glyphs.chunk_by(|a, b| a.cluster == b.cluster) {
+                let start = cluster[0].cluster;
+                let end = starts
+                    .iter()
+                    .find(|s| **s > start)
+                    .copied()
+                    .unwrap_or(text.len());
+                clusters.push((text[start..end].to_string(), cluster.to_vec()));
+            }
+        }
+        clusters
+    }
+
+    /// Splits the element into runs of text in visual order, each with its horizontal
+    /// offset inside a box of `width` and its resolved direction. Justified lines are
+    /// drawn word by word so that they span the box.
+    pub fn text_runs(&self, width: f32, font_dict: &FontDict) -> Vec<(f32, String, Direction)> {
+        let runs = self.direction.visual_runs(&self.item);
+
+        if !matches!(self.alignment(), Alignment::Justified) {
+            let mut offset = 0.0;
+            return runs
+                .into_iter()
+                .map(|(text, direction)| {
+                    let positioned = (offset, text, direction);
+                    offset += self
+                        .shape_text(&positioned.1, Some(direction), font_dict)
+                        .width;
+                    positioned
+                })
+                .collect();
+        }
+
+        // Words of a right-to-left run are read from the right, so they are laid out backwards
+        let words = runs
+            .iter()
+            .flat_map(|(text, direction)| {
+                let words = text.split_whitespace().map(move |word| (word, *direction));
+                match direction {
+                    Direction::Ltr => words.collect::<Vec<_>>(),
+                    Direction::Rtl => words.rev().collect::<Vec<_>>(),
+                }
+            })
+            .collect::<Vec<(&str, Direction)>>();
+        let widths = words
+            .iter()
+            .map(|(word, direction)| self.shape_text(word, Some(*direction), font_dict).width)
+            .collect::<Vec<f32>>();
+
+        let word_spacing = if words.len() < 2 {
+            0.0
+        } else {
+            (width - widths.iter().sum::<f32>()) / (words.len() - 1) as f32
+        };
+
+        let mut offset = 0.0;
+        words
+            .into_iter()
+            .zip(widths)
+            .map(|((word, direction), word_width)| {
+                let positioned = (offset, word.to_string(), direction);
+                offset += word_width + word_spacing;
+                positioned
+            })
+            .collect()
     }
 
     pub fn bound_width(&self, width: f32) -> Element {
@@ -244,8 +930,21 @@ impl Element {
                 width: Width::Absolute(f32::min(self.width.get_fixed_unchecked(), width)),
                 text_width: self.text_width,
                 font: self.font.clone(),
+                hyphenation: self.hyphenation.clone(),
+                color: self.color,
+                background: self.background,
+                shape: self.shape,
+                image: self.image.clone(),
                 is_fill: false,
                 url: self.url.clone(),
+                direction: self.direction,
+                overflow: self.overflow,
+                field: self.field.clone(),
+                max_lines: self.max_lines,
+                ellipsis: self.ellipsis,
+                text_transform: self.text_transform,
+                letter_spacing: self.letter_spacing,
+                style: self.style.clone(),
                 uid: self.uid,
             }
         } else {
@@ -253,11 +952,24 @@ impl Element {
                 item: self.item.clone(),
                 margin: self.margin,
                 alignment: self.alignment,
-                width: Width::Absolute(width),
+                width: self.width.bound(width),
                 text_width: self.text_width,
                 font: self.font.clone(),
+                hyphenation: self.hyphenation.clone(),
+                color: self.color,
+                background: self.background,
+                shape: self.shape,
+                image: self.image.clone(),
                 is_fill: true,
                 url: self.url.clone(),
+                direction: self.direction,
+                overflow: self.overflow,
+                field: self.field.clone(),
+                max_lines: self.max_lines,
+                ellipsis: self.ellipsis,
+                text_transform: self.text_transform,
+                letter_spacing: self.letter_spacing,
+                style: self.style.clone(),
                 uid: self.uid,
             }
         }
@@ -267,51 +979,331 @@ impl Element {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{font::FontLoader, layout::SectionLayout};
 
     #[test]
     fn test_break_lines() {
         let mut font_dict = FontDict::new();
-        let font_data = include_bytes!("../assets/Exo/static/Exo-Medium.ttf");
-        // This only succeeds if collection consists of one font
-        let _font =
-            rusttype::Font::try_from_bytes(font_data as &[u8]).expect("Error constructing Font");
-        font_dict.insert("Arial".to_string(), _font);
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".into(),
+        );
 
         let element = Element {
             item: "hello world".to_string(),
-            margin: Margin::default(),
-            alignment: Alignment::default(),
+            margin: None,
+            alignment: None,
             width: Width::Absolute(100.0),
             text_width: Width::default(),
-            font: Font::default(),
+            font: PartialFont::default(),
+            hyphenation: None,
+            color: None,
+            background: None,
+            shape: None,
+            image: None,
             is_fill: false,
             url: None,
+            direction: Direction::default(),
+            overflow: Overflow::default(),
+            field: None,
+            max_lines: None,
+            ellipsis: Element::default_ellipsis(),
+            text_transform: TextTransform::default(),
+            letter_spacing: 0.0,
+            style: None,
             uid: Uuid::new_v4(),
         };
 
         let element = element.fill_fonts(&font_dict);
 
-        let lines = element.break_lines(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
         assert_eq!(lines.len(), 1);
         assert_eq!(lines[0].item, "hello world");
 
         let element = Element {
             item: "hello world".to_string(),
-            margin: Margin::default(),
-            alignment: Alignment::default(),
-            width: Width::Absolute(22.0),
+            margin: None,
+            alignment: None,
+            width: Width::Absolute(40.0),
             text_width: Width::default(),
-            font: Font::default(),
+            font: PartialFont::default(),
+            hyphenation: None,
+            color: None,
+            background: None,
+            shape: None,
+            image: None,
             is_fill: false,
             url: None,
+            direction: Direction::default(),
+            overflow: Overflow::default(),
+            field: None,
+            max_lines: None,
+            ellipsis: Element::default_ellipsis(),
+            text_transform: TextTransform::default(),
+            letter_spacing: 0.0,
+            style: None,
             uid: Uuid::new_v4(),
         };
 
         let element = element.fill_fonts(&font_dict);
 
-        let lines = element.break_lines(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
         assert_eq!(lines.len(), 2);
         assert_eq!(lines[0].item, "hello");
         assert_eq!(lines[1].item, "world");
     }
+
+    #[test]
+    fn test_justified_lines() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+
+        let element = Element::default()
+            .with_item("the quick brown fox jumps over the lazy dog".to_string())
+            .with_alignment(Alignment::Justified)
+            .with_width(Width::Absolute(100.0))
+            .fill_fonts(&font_dict);
+
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        assert!(lines.len() > 1);
+        let (last, rest) = lines.split_last().unwrap();
+        assert!(matches!(last.alignment(), Alignment::Left));
+        assert!(rest
+            .iter()
+            .all(|line| matches!(line.alignment(), Alignment::Justified)));
+
+        let line = &lines[0];
+        let runs = line.text_runs(100.0, &font_dict);
+        let (offset, word, _) = runs.last().unwrap();
+        assert_eq!(runs[0].0, 0.0);
+        assert!((offset + line.font().get_width(word, &font_dict) - 100.0).abs() < 0.001);
+
+        let single = Element::default()
+            .with_item("one".to_string())
+            .with_alignment(Alignment::Justified)
+            .with_width(Width::Absolute(100.0))
+            .fill_fonts(&font_dict);
+        assert!(matches!(
+            single.break_lines(&font_dict, &mut Vec::new())[0].alignment(),
+            Alignment::Left
+        ));
+    }
+
+    #[test]
+    fn test_break_opportunities() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+
+        // Chinese and Japanese can be broken between any two characters. The opportunities
+        // come from UAX #14 rather than from the font, Exo has no CJK glyphs so every
+        // character is measured as its missing glyph box here.
+        let text = "日本語のテキストは空白なしで書かれます";
+        let element = Element::default()
+            .with_item(text.to_string())
+            .with_width(Width::Absolute(60.0))
+            .fill_fonts(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        assert!(lines.len() > 1);
+        assert_eq!(
+            lines.iter().map(|l| l.item.clone()).collect::<String>(),
+            text
+        );
+
+        // A token wider than the box is broken wherever it has to be
+        let element = Element::default()
+            .with_item("see https://example.com/averyveryverylongpathwithoutbreaks".to_string())
+            .with_width(Width::Absolute(60.0))
+            .fill_fonts(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        assert_eq!(lines[0].item, "see");
+        assert!(lines
+            .iter()
+            .all(|l| l.text_width.get_fixed_unchecked() <= 60.0));
+
+        // Combining marks stay with the letter they are written on
+        let text = "e\u{301}".repeat(20);
+        let element = Element::default()
+            .with_item(text.clone())
+            .with_width(Width::Absolute(30.0))
+            .fill_fonts(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.item.starts_with('e')));
+        assert_eq!(
+            lines.iter().map(|l| l.item.clone()).collect::<String>(),
+            text
+        );
+
+        let element = Element::default()
+            .with_item("first\nsecond".to_string())
+            .with_width(Width::Absolute(500.0))
+            .fill_fonts(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].item, "second");
+    }
+
+    #[test]
+    fn test_hyphenation() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+
+        let element = Element::default()
+            .with_item("an extraordinarily comprehensive internationalization".to_string())
+            .with_width(Width::Absolute(70.0))
+            .with_hyphenation(Some(Hyphenation::default()))
+            .fill_fonts(&font_dict);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        let hyphenated = lines.iter().filter(|l| l.item.ends_with('-')).count();
+        assert!(hyphenated > 0);
+        assert!(lines
+            .iter()
+            .all(|l| l.text_width.get_fixed_unchecked() <= 70.0));
+        // At most two lines in a row end with a hyphen
+        assert!(lines
+            .windows(3)
+            .all(|w| !w.iter().all(|l| l.item.ends_with('-'))));
+
+        // The setting of the layout schema only applies to elements without their own
+        let schema = Some(Hyphenation {
+            max_consecutive_hyphens: 0,
+            ..Hyphenation::default()
+        });
+        let inherited = element.inherit_hyphenation(&schema);
+        assert_eq!(inherited.hyphenation, Some(Hyphenation::default()));
+        let element = element.with_hyphenation(None).inherit_hyphenation(&schema);
+        let lines = element.break_lines(&font_dict, &mut Vec::new());
+        assert!(lines.iter().all(|l| !l.item.ends_with('-')));
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+
+        let summary = Element {
+            max_lines: Some(2),
+            ..Element::default()
+                .with_item("one two three four five six seven eight nine ten".to_string())
+                .with_width(Width::Absolute(60.0))
+                .with_field("Summary".to_string())
+        }
+        .fill_fonts(&font_dict);
+        let mut diagnostics = Vec::new();
+        let lines = summary.break_lines(&font_dict, &mut diagnostics);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].item.ends_with('…'));
+        assert!(lines[1].text_width.get_fixed_unchecked() <= 60.0);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].field, "Summary");
+
+        // The hidden lines are reported by the layout of the section as well
+        let (_, diagnostics) =
+            SectionLayout::Text(summary.clone()).normalize_with_diagnostics(500.0, &font_dict);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.ends_with("hidden by max_lines"));
+
+        // Text is cut after a letter and its combining mark, never between them
+        let text = "e\u{301}".repeat(10);
+        let cut = summary.cut(&text, 30.0, "", &font_dict);
+        assert!(!cut.is_empty() && cut.len() < text.len());
+        assert!(cut.ends_with('\u{301}'));
+
+        let summary = Element {
+            ellipsis: false,
+            ..summary
+        };
+        let lines = summary.break_lines(&font_dict, &mut Vec::new());
+        assert_eq!(lines.len(), 2);
+        assert!(!lines[1].item.ends_with('…'));
+
+        // Text within the maximum is left as it is
+        let summary = Element {
+            max_lines: Some(10),
+            ..summary
+        };
+        let mut diagnostics = Vec::new();
+        summary.break_lines(&font_dict, &mut diagnostics);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_text_transform() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+
+        let plain = Element::default()
+            .with_item("work experience".to_string())
+            .with_width(Width::Absolute(200.0))
+            .fill_fonts(&font_dict);
+        let header = Element {
+            text_transform: TextTransform::Upper,
+            letter_spacing: 2.0,
+            ..plain.clone()
+        }
+        .fill_fonts(&font_dict);
+        assert_eq!(header.item, "WORK EXPERIENCE");
+
+        // Every letter and space is followed by the spacing, in the measure and the glyphs
+        let width = header.text_width.get_fixed_unchecked();
+        let unspaced = header.font().get_width(&header.item, &font_dict);
+        assert!((width - unspaced - 2.0 * 15.0).abs() < 0.001);
+        let glyphs = header.shape(width, &font_dict);
+        let unspaced_glyphs = header.font().shape(&header.item, &font_dict).glyphs;
+        assert!((glyphs[1].x - unspaced_glyphs[1].x - 2.0).abs() < 0.001);
+        assert!(width > plain.text_width.get_fixed_unchecked());
+
+        // Narrow boxes wrap at the spaced width
+        let lines = header
+            .with_width(Width::Absolute(unspaced))
+            .break_lines(&font_dict, &mut Vec::new());
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_shape_clusters() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+
+        // Every glyph is drawn with the text it was shaped from, a letter and its combining
+        // mark or the letters of a ligature are one cluster
+        let element = Element::default()
+            .with_item("Cafe\u{301} office".to_string())
+            .with_width(Width::Absolute(200.0))
+            .fill_fonts(&font_dict);
+        let clusters = element.shape_clusters(200.0, &font_dict);
+        assert_eq!(
+            clusters.iter().map(|(t, _)| t.as_str()).collect::<String>(),
+            element.item
+        );
+        assert!(clusters.iter().any(|(t, _)| t == "e\u{301}"));
+        assert!(clusters
+            .iter()
+            .any(|(t, glyphs)| t == "ffi" && glyphs.len() == 1));
+        assert_eq!(
+            clusters
+                .iter()
+                .flat_map(|(_, g)| g.clone())
+                .collect::<Vec<_>>(),
+            element.shape(200.0, &font_dict)
+        );
+    }
 }
@@ -1,20 +1,19 @@
-use std::{
-    collections::HashMap,
-    fs::{self, File},
-    io::Read,
-};
+use std::{collections::HashMap, fmt, fs::File, io::Read, str::FromStr};
 
 use font_kit::{
     family_name::FamilyName,
     properties::{Properties, Stretch, Style, Weight},
     source::SystemSource,
 };
-use rusttype::{point, Scale};
-use serde::{Deserialize, Serialize};
+use rusttype::{GlyphId, Scale};
+use serde::{de::Visitor, Deserialize, Serialize};
 
-use crate::layout_schema::LayoutSchema;
+use crate::{
+    color::Color, direction::Direction, font_registry::FontRegistry, layout_schema::LayoutSchema,
+    shaping::ShapedText, variable_font,
+};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Font {
     #[serde(default = "Font::default_name")]
     pub name: String,
@@ -22,59 +21,197 @@ pub struct Font {
     pub size: f32,
     #[serde(default = "FontWeight::default")]
     pub weight: FontWeight,
+    #[serde(default = "FontStretch::default")]
+    pub stretch: FontStretch,
     #[serde(default = "FontStyle::default")]
     pub style: FontStyle,
     #[serde(default = "FontSource::default")]
     pub source: FontSource,
+    // Height of a line as a multiple of the font height
+    #[serde(default = "Font::default_line_height")]
+    pub line_height: f32,
+    #[serde(default = "Color::default")]
+    pub color: Color,
+    // Families to take glyphs from when this font does not have them, in order
+    #[serde(default = "Vec::new")]
+    pub fallbacks: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub enum FontSource {
     Local,
     #[default]
     System,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub enum FontWeight {
-    Light,
-    #[default]
-    Medium,
-    Bold,
+/// Weight of a font on the usual scale from 100 to 900. It is written either as a number,
+/// or as the name of one of the multiples of 100, such as "Light" or "Bold".
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontWeight(pub u16);
+
+impl FontWeight {
+    const NAMES: [(u16, &'static str); 9] = [
+        (100, "Thin"),
+        (200, "ExtraLight"),
+        (300, "Light"),
+        (400, "Regular"),
+        (500, "Medium"),
+        (600, "SemiBold"),
+        (700, "Bold"),
+        (800, "ExtraBold"),
+        (900, "Black"),
+    ];
 }
 
-impl ToString for FontWeight {
-    fn to_string(&self) -> String {
-        match self {
-            FontWeight::Light => "Light".to_string(),
-            FontWeight::Medium => "Medium".to_string(),
-            FontWeight::Bold => "Bold".to_string(),
+impl Default for FontWeight {
+    fn default() -> FontWeight {
+        FontWeight(500)
+    }
+}
+
+impl FromStr for FontWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FontWeight, Self::Err> {
+        let weight = match s.trim() {
+            "Normal" => 400,
+            s => FontWeight::NAMES
+                .iter()
+                .find(|(_, name)| *name == s)
+                .map(|(weight, _)| *weight)
+                .or_else(|| s.parse::<u16>().ok())
+                .ok_or_else(|| format!("invalid font weight: {}", s))?,
+        };
+
+        if !(100..=900).contains(&weight) {
+            return Err(format!(
+                "font weight must be between 100 and 900: {}",
+                weight
+            ));
         }
+
+        Ok(FontWeight(weight))
+    }
+}
+
+impl fmt::Display for FontWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match FontWeight::NAMES
+            .iter()
+            .find(|(weight, _)| *weight == self.0)
+        {
+            Some((_, name)) => write!(f, "{}", name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for FontWeight {
+    type Value = FontWeight;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a font weight from 100 to 900, or its name")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse::<FontWeight>().map_err(E::custom)
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_str(&v.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for FontWeight {
+    fn deserialize<D>(deserializer: D) -> Result<FontWeight, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(FontWeight::default())
+    }
+}
+
+impl Serialize for FontWeight {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u16(self.0)
     }
 }
 
 impl From<FontWeight> for Weight {
     fn from(val: FontWeight) -> Self {
-        match val {
-            FontWeight::Light => Weight::LIGHT,
-            FontWeight::Medium => Weight::MEDIUM,
-            FontWeight::Bold => Weight::BOLD,
+        Weight(val.0 as f32)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum FontStretch {
+    UltraCondensed,
+    ExtraCondensed,
+    Condensed,
+    SemiCondensed,
+    #[default]
+    Normal,
+    SemiExpanded,
+    Expanded,
+    ExtraExpanded,
+    UltraExpanded,
+}
+
+impl FontStretch {
+    // Width of the font as a percentage of the normal width, as used by the `wdth` axis
+    pub fn percentage(&self) -> f32 {
+        match self {
+            FontStretch::UltraCondensed => 50.0,
+            FontStretch::ExtraCondensed => 62.5,
+            FontStretch::Condensed => 75.0,
+            FontStretch::SemiCondensed => 87.5,
+            FontStretch::Normal => 100.0,
+            FontStretch::SemiExpanded => 112.5,
+            FontStretch::Expanded => 125.0,
+            FontStretch::ExtraExpanded => 150.0,
+            FontStretch::UltraExpanded => 200.0,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+impl fmt::Display for FontStretch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontStretch::Normal => Ok(()),
+            stretch => write!(f, "{:?}", stretch),
+        }
+    }
+}
+
+impl From<FontStretch> for Stretch {
+    fn from(val: FontStretch) -> Self {
+        Stretch(val.percentage() / 100.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub enum FontStyle {
     #[default]
     Normal,
     Italic,
+    Oblique,
 }
 
-impl ToString for FontStyle {
-    fn to_string(&self) -> String {
+impl fmt::Display for FontStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            FontStyle::Normal => "".to_string(),
-            FontStyle::Italic => "Italic".to_string(),
+            FontStyle::Normal => Ok(()),
+            FontStyle::Italic => write!(f, "Italic"),
+            FontStyle::Oblique => write!(f, "Oblique"),
         }
     }
 }
@@ -84,6 +221,7 @@ impl From<FontStyle> for Style {
         match val {
             FontStyle::Normal => Style::Normal,
             FontStyle::Italic => Style::Italic,
+            FontStyle::Oblique => Style::Oblique,
         }
     }
 }
@@ -94,8 +232,12 @@ impl Default for Font {
             name: Font::default_name(),
             size: Font::default_size(),
             weight: FontWeight::default(),
+            stretch: FontStretch::default(),
             style: FontStyle::default(),
             source: FontSource::default(),
+            line_height: Font::default_line_height(),
+            color: Color::default(),
+            fallbacks: Vec::new(),
         }
     }
 }
@@ -108,6 +250,84 @@ impl Font {
     pub fn default_size() -> f32 {
         12.0
     }
+
+    pub fn default_line_height() -> f32 {
+        1.0
+    }
+}
+
+/// A font of the layout schemas, where only the properties written in the document are
+/// set. Fonts of elements and styles are merged property by property, the properties no
+/// font sets are the ones of `Font::default()`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PartialFont {
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub size: Option<f32>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub weight: Option<FontWeight>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub stretch: Option<FontStretch>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub style: Option<FontStyle>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub source: Option<FontSource>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub line_height: Option<f32>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub fallbacks: Option<Vec<String>>,
+}
+
+impl PartialFont {
+    // This font with the properties it leaves unset taken from `parent`
+    pub fn over(&self, parent: &PartialFont) -> PartialFont {
+        PartialFont {
+            name: self.name.clone().or_else(|| parent.name.clone()),
+            size: self.size.or(parent.size),
+            weight: self.weight.clone().or_else(|| parent.weight.clone()),
+            stretch: self.stretch.clone().or_else(|| parent.stretch.clone()),
+            style: self.style.clone().or_else(|| parent.style.clone()),
+            source: self.source.clone().or_else(|| parent.source.clone()),
+            line_height: self.line_height.or(parent.line_height),
+            color: self.color.or(parent.color),
+            fallbacks: self.fallbacks.clone().or_else(|| parent.fallbacks.clone()),
+        }
+    }
+
+    // The font with the properties it leaves unset at their defaults
+    pub fn resolve(&self) -> Font {
+        let default = Font::default();
+        Font {
+            name: self.name.clone().unwrap_or(default.name),
+            size: self.size.unwrap_or(default.size),
+            weight: self.weight.clone().unwrap_or(default.weight),
+            stretch: self.stretch.clone().unwrap_or(default.stretch),
+            style: self.style.clone().unwrap_or(default.style),
+            source: self.source.clone().unwrap_or(default.source),
+            line_height: self.line_height.unwrap_or(default.line_height),
+            color: self.color.unwrap_or(default.color),
+            fallbacks: self.fallbacks.clone().unwrap_or(default.fallbacks),
+        }
+    }
+}
+
+impl From<Font> for PartialFont {
+    fn from(font: Font) -> PartialFont {
+        PartialFont {
+            name: Some(font.name),
+            size: Some(font.size),
+            weight: Some(font.weight),
+            stretch: Some(font.stretch),
+            style: Some(font.style),
+            source: Some(font.source),
+            line_height: Some(font.line_height),
+            color: Some(font.color),
+            fallbacks: Some(font.fallbacks),
+        }
+    }
 }
 
 pub enum FontLoadSource {
@@ -117,14 +337,53 @@ pub enum FontLoadSource {
 pub struct LoadedFont {
     pub source: FontLoadSource,
     pub rusttype_font: rusttype::Font<'static>,
+    // Raw font file, used for shaping and embedding. Variable fonts are instanced
+    // at the weight and the stretch of the font before they are stored here.
+    font_data: Vec<u8>,
+}
+
+impl LoadedFont {
+    pub fn new(source: FontLoadSource, font_data: Vec<u8>) -> LoadedFont {
+        let rusttype_font = rusttype::Font::try_from_vec(font_data.clone()).unwrap();
+        LoadedFont {
+            source,
+            rusttype_font,
+            font_data,
+        }
+    }
+
+    pub fn font_data(&self) -> &[u8] {
+        &self.font_data
+    }
+
+    // Parsing the face is lazy, so it is parsed again wherever it is needed
+    pub fn face(&self) -> Option<rustybuzz::Face<'_>> {
+        rustybuzz::Face::from_slice(&self.font_data, 0)
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.rusttype_font.glyph(c).id() != GlyphId(0)
+    }
+
+    // Whether the substitutions of the font include the OpenType feature `tag`
+    pub fn has_feature(&self, tag: rustybuzz::ttf_parser::Tag) -> bool {
+        self.face()
+            .and_then(|face| face.tables().gsub)
+            .is_some_and(|gsub| gsub.features.find(tag).is_some())
+    }
 }
 
 pub type FontDict = HashMap<String, LoadedFont>;
 
 pub trait FontLoader {
     fn load_font_from_path(&mut self, name: String, path: String);
-    fn load_fonts_from_schema(&mut self, layout_schema: &LayoutSchema);
-    fn load_font(&mut self, font: &Font);
+    fn load_fonts_from_schema(
+        &mut self,
+        layout_schema: &LayoutSchema,
+        font_registry: &FontRegistry,
+    );
+    fn load_font_chain(&mut self, font: &Font, font_registry: &FontRegistry);
+    fn load_font(&mut self, font: &Font, font_registry: &FontRegistry);
 }
 
 impl FontLoader for FontDict {
@@ -133,22 +392,33 @@ impl FontLoader for FontDict {
         let mut bytes = Vec::new();
         file.read_to_end(&mut bytes).unwrap();
 
-        let rusttype_font = rusttype::Font::try_from_vec(bytes.clone()).unwrap();
-        self.insert(
-            name,
-            LoadedFont {
-                source: FontLoadSource::Local(path),
-                rusttype_font,
-            },
-        );
+        self.insert(name, LoadedFont::new(FontLoadSource::Local(path), bytes));
     }
 
-    fn load_font(&mut self, font: &Font) {
+    fn load_font(&mut self, font: &Font, font_registry: &FontRegistry) {
         match font.source {
             FontSource::Local => {
-                self.load_font_from_path(
+                let Some(face) = font_registry.find(font) else {
+                    log::info!(
+                        "{} was not found in the font search paths, will use the default font",
+                        font.full_name()
+                    );
+
+                    if !self.contains_key(&Font::default().full_name()) {
+                        let default_font = Font::default();
+                        self.load_font(&default_font, font_registry);
+                    }
+                    return;
+                };
+
+                let path = face.path.to_string_lossy().to_string();
+                let mut file = File::open(path.clone()).unwrap();
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).unwrap();
+
+                self.insert(
                     font.full_name(),
-                    format!("assets/{}/static/{}.ttf", font.name, font.full_name()),
+                    LoadedFont::new(FontLoadSource::Local(path), font.instance(bytes)),
                 );
             }
             FontSource::System => {
@@ -157,22 +427,20 @@ impl FontLoader for FontDict {
                     &Properties {
                         style: font.style.clone().into(),
                         weight: font.weight.clone().into(),
-                        stretch: Stretch::NORMAL,
+                        stretch: font.stretch.clone().into(),
                     },
                 ) {
                     let font_data = best_match.load().unwrap();
                     let font_stream = font_data.copy_font_data().unwrap();
-                    let rusttype_font =
-                        rusttype::Font::try_from_vec((*font_stream).clone()).unwrap();
 
                     log::info!("{} will be used in your document", font_data.full_name());
 
                     self.insert(
                         font.full_name(),
-                        LoadedFont {
-                            source: FontLoadSource::System(font_data),
-                            rusttype_font,
-                        },
+                        LoadedFont::new(
+                            FontLoadSource::System(font_data),
+                            font.instance((*font_stream).clone()),
+                        ),
                     );
                 } else {
                     log::info!(
@@ -180,19 +448,38 @@ impl FontLoader for FontDict {
                         font.full_name()
                     );
 
-                    if !self.contains_key(&Font::default_name()) {
-                        let default_font = Font::default();
-                        self.load_font(&default_font);
+                    // The default font is loaded once, and not again when it is missing itself
+                    let default_font = Font::default();
+                    if font.full_name() == default_font.full_name() {
+                        log::error!("The default font {} is missing", font.full_name());
+                    } else if !self.contains_key(&default_font.full_name()) {
+                        self.load_font(&default_font, font_registry);
                     }
                 }
             }
         }
     }
 
-    fn load_fonts_from_schema(&mut self, layout_schema: &LayoutSchema) {
+    fn load_fonts_from_schema(
+        &mut self,
+        layout_schema: &LayoutSchema,
+        font_registry: &FontRegistry,
+    ) {
         for font in layout_schema.fonts() {
+            self.load_font_chain(&font, font_registry);
+        }
+    }
+
+    fn load_font_chain(&mut self, font: &Font, font_registry: &FontRegistry) {
+        let chain = font.font_chain();
+        // Fallbacks are taken from the font search paths first, then from the system
+        let fallbacks = chain[1..].iter().map(|f| match font_registry.find(f) {
+            Some(_) => f.with_source(FontSource::Local),
+            None => f.clone(),
+        });
+        for font in std::iter::once(chain[0].clone()).chain(fallbacks) {
             if !self.contains_key(&font.full_name()) {
-                self.load_font(&font);
+                self.load_font(&font, font_registry);
             }
         }
     }
@@ -200,53 +487,193 @@ impl FontLoader for FontDict {
 
 impl Font {
     pub fn full_name(&self) -> String {
-        self.name.clone() + "-" + self.weight.to_string().as_str() + self.style.to_string().as_str()
+        self.name.clone()
+            + "-"
+            + self.stretch.to_string().as_str()
+            + self.weight.to_string().as_str()
+            + self.style.to_string().as_str()
     }
 
-    pub fn get_width(&self, text: &str, font_dict: &FontDict) -> f32 {
-        let text = text.trim();
-        
-        if text.len() == 0 {
-            return 0.0;
-        }
-        
-        // The font size to use
-        let scale = Scale::uniform(self.size);
-        let font = &font_dict
+    // Instances variable fonts at the weight and the stretch of this font
+    fn instance(&self, font_data: Vec<u8>) -> Vec<u8> {
+        variable_font::instance(&font_data, self.weight.0 as f32, self.stretch.percentage())
+            .unwrap_or(font_data)
+    }
+
+    // Falls back to the default font if this one could not be loaded
+    pub fn loaded_font<'a>(&self, font_dict: &'a FontDict) -> &'a LoadedFont {
+        font_dict
             .get(&self.full_name())
             .unwrap_or_else(|| font_dict.get(&Font::default().full_name()).unwrap())
-            .rusttype_font;
+    }
 
-        // The text to render
-        let v_metrics = font.v_metrics(scale);
+    pub fn with_fallbacks(&self, fallbacks: Vec<String>) -> Font {
+        Font {
+            name: self.name.clone(),
+            size: self.size,
+            weight: self.weight.clone(),
+            stretch: self.stretch.clone(),
+            style: self.style.clone(),
+            source: self.source.clone(),
+            line_height: self.line_height,
+            color: self.color,
+            fallbacks,
+        }
+    }
+
+    pub fn with_size(&self, size: f32) -> Font {
+        Font {
+            name: self.name.clone(),
+            size,
+            weight: self.weight.clone(),
+            stretch: self.stretch.clone(),
+            style: self.style.clone(),
+            source: self.source.clone(),
+            line_height: self.line_height,
+            color: self.color,
+            fallbacks: self.fallbacks.clone(),
+        }
+    }
+
+    pub fn with_source(&self, source: FontSource) -> Font {
+        Font {
+            name: self.name.clone(),
+            size: self.size,
+            weight: self.weight.clone(),
+            stretch: self.stretch.clone(),
+            style: self.style.clone(),
+            source,
+            line_height: self.line_height,
+            color: self.color,
+            fallbacks: self.fallbacks.clone(),
+        }
+    }
 
-        // layout the glyphs in a line with 20 pixels padding
-        let glyphs: Vec<_> = font
-            .layout(text.trim(), scale, point(0_f32, v_metrics.ascent))
-            .collect();
-
-        let glyphs_width = {
-            let min_x = glyphs
-                .first()
-                .map(|g| g.pixel_bounding_box().unwrap().min.x)
-                .unwrap();
-            let max_x = glyphs
-                .last()
-                .map(|g| g.pixel_bounding_box().unwrap().max.x)
-                .unwrap();
-            (max_x - min_x) as f32
+    // Appends the document-wide fallbacks after the ones of the font
+    pub fn inherit_fallbacks(&self, fallbacks: &[String]) -> Font {
+        self.with_fallbacks([self.fallbacks.as_slice(), fallbacks].concat())
+    }
+
+    /// The font followed by its fallbacks. Fallbacks are families of the font search paths
+    /// or of the system, used with the size, weight, stretch and style of the font they
+    /// stand in for.
+    pub fn font_chain(&self) -> Vec<Font> {
+        let fallbacks = self.fallbacks.iter().map(|name| Font {
+            name: name.clone(),
+            size: self.size,
+            weight: self.weight.clone(),
+            stretch: self.stretch.clone(),
+            style: self.style.clone(),
+            source: FontSource::System,
+            line_height: self.line_height,
+            color: self.color,
+            fallbacks: Vec::new(),
+        });
+
+        std::iter::once(self.with_fallbacks(Vec::new()))
+            .chain(fallbacks)
+            .collect()
+    }
+
+    // Splits `text` into runs that are drawn with the same font of the fallback chain.
+    // Every character is taken from the first font that has a glyph for it, whitespace
+    // stays in the run it is in.
+    fn font_runs<'a>(&self, text: &'a str, font_dict: &FontDict) -> Vec<(String, &'a str)> {
+        // Like `loaded_font`, a font that could not be loaded is replaced by the default font
+        let primary = match font_dict.contains_key(&self.full_name()) {
+            true => self.full_name(),
+            false => Font::default().full_name(),
         };
+        let chain = std::iter::once(primary)
+            .chain(
+                self.font_chain()
+                    .iter()
+                    .skip(1)
+                    .map(|f| f.full_name())
+                    .filter(|name| font_dict.contains_key(name)),
+            )
+            .collect::<Vec<String>>();
+
+        let mut runs: Vec<(String, &'a str)> = Vec::new();
+        let mut run_start = 0;
+        let mut run_font: Option<&String> = None;
+
+        for (index, c) in text.char_indices() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let font = chain
+                .iter()
+                .find(|name| font_dict[*name].has_glyph(c))
+                .unwrap_or(&chain[0]);
+            match run_font {
+                Some(current) if current != font => {
+                    runs.push((current.clone(), &text[run_start..index]));
+                    run_start = index;
+                    run_font = Some(font);
+                }
+                Some(_) => {}
+                None => run_font = Some(font),
+            }
+        }
+        runs.push((run_font.unwrap_or(&chain[0]).clone(), &text[run_start..]));
+
+        runs
+    }
+
+    /// Shapes `text` with the OpenType `features` turned on, runs of glyphs missing from
+    /// the font are shaped with its fallbacks.
+    pub fn shape_runs(
+        &self,
+        text: &str,
+        direction: Option<Direction>,
+        features: &[rustybuzz::Feature],
+        font_dict: &FontDict,
+    ) -> ShapedText {
+        // Clusters are byte indices into the whole text rather than into each run
+        let mut start = 0;
+        let mut runs = self
+            .font_runs(text, font_dict)
+            .into_iter()
+            .map(|(name, run)| {
+                let font = &font_dict[&name];
+                let shaped = ShapedText::shape(run, &name, font, self.size, direction, features)
+                    .move_clusters_by(start);
+                start += run.len();
+                shaped
+            })
+            .collect::<Vec<ShapedText>>();
+
+        // Right-to-left runs are read from the right, so the first one is drawn last
+        if direction == Some(Direction::Rtl) {
+            runs.reverse();
+        }
+
+        runs.into_iter()
+            .reduce(ShapedText::concat)
+            .unwrap_or_default()
+    }
+
+    pub fn shape(&self, text: &str, font_dict: &FontDict) -> ShapedText {
+        self.shape_runs(text, None, &[], font_dict)
+    }
+
+    pub fn get_width(&self, text: &str, font_dict: &FontDict) -> f32 {
+        let text = text.trim();
 
-        glyphs_width
+        if text.is_empty() {
+            return 0.0;
+        }
+
+        // The width is the advance of the shaped text, which is also how the backends
+        // position glyphs, so measured and rendered widths agree.
+        self.shape(text, font_dict).width
     }
 
     pub fn get_height(&self, font_dict: &FontDict) -> f32 {
         // The font size to use
         let scale = Scale::uniform(self.size);
-        let font = &font_dict
-            .get(&self.full_name())
-            .unwrap_or_else(|| font_dict.get(&Font::default().full_name()).unwrap())
-            .rusttype_font;
+        let font = &self.loaded_font(font_dict).rusttype_font;
 
         // The text to render
         let v_metrics = font.v_metrics(scale);
@@ -255,7 +682,25 @@ impl Font {
         v_metrics.ascent - v_metrics.descent
     }
 
-    pub fn get_available_fonts() -> (Vec<String>, Vec<String>) {
+    pub fn get_ascent(&self, font_dict: &FontDict) -> f32 {
+        let scale = Scale::uniform(self.size);
+        let font = &self.loaded_font(font_dict).rusttype_font;
+
+        font.v_metrics(scale).ascent
+    }
+
+    pub fn get_line_height(&self, font_dict: &FontDict) -> f32 {
+        self.get_height(font_dict) * self.line_height
+    }
+
+    // Distance from the top of a line to its baseline, the extra leading of the line
+    // is split evenly above and below the text
+    pub fn get_baseline(&self, font_dict: &FontDict) -> f32 {
+        let leading = self.get_line_height(font_dict) - self.get_height(font_dict);
+        leading / 2.0 + self.get_ascent(font_dict)
+    }
+
+    pub fn get_available_fonts(font_registry: &FontRegistry) -> (Vec<String>, Vec<String>) {
         let ss = SystemSource::new();
         let system_fonts = ss.all_families().unwrap();
 
@@ -263,16 +708,39 @@ impl Font {
 
         log::info!("Discovering locally installed fonts...");
 
-        let local_fonts: Vec<String> = fs::read_dir("assets")
-            .unwrap()
-            .filter_map(|t| t.ok())
-            .filter(|dir_entry| dir_entry.file_type().map_or(false, |f| f.is_dir()))
-            .map(|dir_entry| dir_entry.file_name().into_string())
-            .filter_map(|t| t.ok())
-            .collect();
+        let local_fonts = font_registry.families();
 
         log::info!("{} local fonts have been discovered!", local_fonts.len());
 
         (system_fonts, local_fonts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_weight() {
+        let weights: Vec<FontWeight> =
+            serde_json::from_str(r#"["Bold", "Normal", 300, "450"]"#).unwrap();
+        assert_eq!(
+            weights,
+            vec![
+                FontWeight(700),
+                FontWeight(400),
+                FontWeight(300),
+                FontWeight(450)
+            ]
+        );
+        assert!(serde_json::from_str::<FontWeight>("1000").is_err());
+        assert!(serde_json::from_str::<FontWeight>(r#""Heavy""#).is_err());
+
+        let font: Font = serde_json::from_str(
+            r#"{ "name": "Exo", "weight": 450, "stretch": "Condensed", "style": "Oblique" }"#,
+        )
+        .unwrap();
+        assert_eq!(font.full_name(), "Exo-Condensed450Oblique");
+        assert_eq!(Font::default().full_name(), "Arial-Medium");
+    }
+}
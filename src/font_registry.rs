@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use directories::UserDirs;
+use ttf_parser::{name_id, Face, Tag};
+
+use crate::{
+    font::{Font, FontStyle},
+    local_storage::LocalStorage,
+};
+
+/// A font file found in one of the search paths of a `FontRegistry`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub family: String,
+    pub weight: u16,
+    // Width of the face as a percentage of the normal width
+    pub stretch: f32,
+    pub italic: bool,
+    // Range of weights a variable face can be instanced at
+    pub weight_range: Option<(f32, f32)>,
+    pub path: PathBuf,
+}
+
+impl FontFace {
+    fn from_path(path: &Path) -> Option<FontFace> {
+        let data = fs::read(path).ok()?;
+        let face = Face::parse(&data, 0).ok()?;
+
+        // Typographic family names group all the weights of a family under one name
+        let family = [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+            .iter()
+            .find_map(|id| {
+                face.names()
+                    .into_iter()
+                    .filter(|name| name.name_id == *id)
+                    .find_map(|name| name.to_string())
+            })?;
+
+        let weight_range = face
+            .variation_axes()
+            .into_iter()
+            .find(|axis| axis.tag == Tag::from_bytes(b"wght"))
+            .map(|axis| (axis.min_value, axis.max_value));
+
+        Some(FontFace {
+            family,
+            weight: face.weight().to_number(),
+            stretch: FontFace::stretch_percentage(face.width().to_number()),
+            italic: face.is_italic() || face.is_oblique(),
+            weight_range,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn stretch_percentage(width_class: u16) -> f32 {
+        match width_class {
+            1 => 50.0,
+            2 => 62.5,
+            3 => 75.0,
+            4 => 87.5,
+            6 => 112.5,
+            7 => 125.0,
+            8 => 150.0,
+            9 => 200.0,
+            _ => 100.0,
+        }
+    }
+
+    pub fn is_variable(&self) -> bool {
+        self.weight_range.is_some()
+    }
+
+    // How far the face is from the requested weight, variable faces are preferred over
+    // static faces of another weight, but not over a static face of the same weight
+    fn weight_distance(&self, weight: f32) -> f32 {
+        match self.weight_range {
+            Some((min, max)) if (min..=max).contains(&weight) => 0.5,
+            Some((min, max)) => (weight - weight.clamp(min, max)).abs(),
+            None => (weight - self.weight as f32).abs(),
+        }
+    }
+}
+
+/// Fonts shipped with the library, installed into the fonts directory of the storage by
+/// `LocalStorage::initiate_local_storage`. The variable fonts cover every weight.
+pub const BUNDLED_FONTS: [(&str, &[u8]); 2] = [
+    (
+        "Exo/Exo-VariableFont_wght.ttf",
+        include_bytes!("../assets/Exo/Exo-VariableFont_wght.ttf"),
+    ),
+    (
+        "Exo/Exo-Italic-VariableFont_wght.ttf",
+        include_bytes!("../assets/Exo/Exo-Italic-VariableFont_wght.ttf"),
+    ),
+];
+
+/// Index of the font files in a list of directories, used to load `FontSource::Local`
+/// fonts without depending on the working directory of the process.
+#[derive(Debug, Clone, Default)]
+pub struct FontRegistry {
+    pub search_paths: Vec<PathBuf>,
+    faces: Vec<FontFace>,
+}
+
+impl FontRegistry {
+    /// Scans `search_paths` and their subdirectories for TrueType and OpenType fonts.
+    /// Paths that do not exist are skipped.
+    pub fn new(search_paths: Vec<PathBuf>) -> FontRegistry {
+        let mut faces = Vec::new();
+        for path in search_paths.iter() {
+            FontRegistry::scan(path, &mut faces);
+        }
+
+        log::info!(
+            "{} local font files have been discovered in {} directories",
+            faces.len(),
+            search_paths.len()
+        );
+
+        FontRegistry {
+            search_paths,
+            faces,
+        }
+    }
+
+    /// The directories searched for local fonts, in lookup order. The paths listed in the
+    /// document come first and are relative to the storage directory, then the fonts of the
+    /// storage, which include the fonts bundled with the library, and the fonts of the user.
+    pub fn search_paths(local_storage: &LocalStorage, document_paths: &[String]) -> Vec<PathBuf> {
+        let mut search_paths: Vec<PathBuf> = document_paths
+            .iter()
+            .map(|path| local_storage.dir().join(path))
+            .collect();
+
+        search_paths.push(local_storage.fonts_dir());
+        if let Some(font_dir) = UserDirs::new().as_ref().and_then(|dirs| dirs.font_dir()) {
+            search_paths.push(font_dir.to_path_buf());
+        }
+
+        search_paths
+    }
+
+    fn scan(path: &Path, faces: &mut Vec<FontFace>) {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+
+        // Entries are sorted so that the lookup does not depend on the file system
+        let mut paths = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+
+        for path in paths {
+            if path.is_dir() {
+                FontRegistry::scan(&path, faces);
+                continue;
+            }
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if extension != "ttf" && extension != "otf" {
+                continue;
+            }
+            match FontFace::from_path(&path) {
+                Some(face) => faces.push(face),
+                None => log::warn!("{} could not be parsed as a font", path.display()),
+            }
+        }
+    }
+
+    pub fn faces(&self) -> &[FontFace] {
+        &self.faces
+    }
+
+    pub fn families(&self) -> Vec<String> {
+        let mut families = self
+            .faces
+            .iter()
+            .map(|face| face.family.clone())
+            .collect::<Vec<String>>();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// The face closest to `font` in its family. Faces of the requested style come first,
+    /// then the ones closest in weight and in stretch.
+    pub fn find(&self, font: &Font) -> Option<&FontFace> {
+        let italic = !matches!(font.style, FontStyle::Normal);
+        let weight = font.weight.0 as f32;
+        let stretch = font.stretch.percentage();
+
+        self.faces
+            .iter()
+            .filter(|face| face.family.eq_ignore_ascii_case(&font.name))
+            .min_by(|a, b| {
+                let key = |face: &FontFace| {
+                    (
+                        face.italic != italic,
+                        face.weight_distance(weight),
+                        (face.stretch - stretch).abs(),
+                    )
+                };
+                key(a).partial_cmp(&key(b)).unwrap()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::FontWeight;
+
+    #[test]
+    fn test_find() {
+        let registry = FontRegistry::new(vec![
+            PathBuf::from("assets"),
+            PathBuf::from("does-not-exist"),
+        ]);
+        assert_eq!(registry.families(), vec!["Exo"]);
+
+        let font = Font {
+            name: "exo".to_string(),
+            weight: FontWeight(700),
+            style: FontStyle::Italic,
+            ..Font::default()
+        };
+        let face = registry.find(&font).unwrap();
+        assert!(face.path.ends_with("static/Exo-BoldItalic.ttf"));
+        assert!(face.italic && !face.is_variable());
+
+        // Weights without a static file are instanced from the variable font
+        let font = Font {
+            weight: FontWeight(450),
+            style: FontStyle::Normal,
+            ..font
+        };
+        let face = registry.find(&font).unwrap();
+        assert!(face.path.ends_with("Exo-VariableFont_wght.ttf"));
+        assert_eq!(face.weight_range, Some((100.0, 900.0)));
+
+        let font = Font {
+            name: "Missing".to_string(),
+            ..font
+        };
+        assert!(registry.find(&font).is_none());
+    }
+}
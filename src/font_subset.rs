@@ -0,0 +1,251 @@
+use std::collections::BTreeSet;
+
+use ttf_parser::{name_id, Face, PlatformId, Tag};
+
+use crate::variable_font;
+
+// OpenType layout and device metrics are only needed for shaping, which is done before
+// the fonts are embedded, so they are left out of the subsets
+const DROPPED_TABLES: [&[u8; 4]; 13] = [
+    b"GSUB", b"GPOS", b"GDEF", b"BASE", b"JSTF", b"MATH", b"kern", b"morx", b"DSIG", b"hdmx",
+    b"LTSH", b"VDMX", b"STAT",
+];
+
+// Tables that are rebuilt for the subsets
+const REBUILT_TABLES: [&[u8; 4]; 5] = [b"glyf", b"loca", b"head", b"post", b"name"];
+
+// Flags of the components of composite glyphs
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+/// Creates a subset of a TrueType font that only has the outlines of `glyphs`, and of
+/// the glyphs they are composed of. Glyph ids are kept, so the shaped text can be written
+/// as it is, the outlines of the other glyphs are emptied. Returns `None` for fonts that
+/// are not TrueType flavored.
+pub fn subset(data: &[u8], glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+    let face = Face::parse(data, 0).ok()?;
+    let raw_face = face.raw_face();
+    let table = |tag: &[u8; 4]| raw_face.table(Tag::from_bytes(tag)).map(<[u8]>::to_vec);
+
+    let glyf = table(b"glyf")?;
+    let loca = table(b"loca")?;
+    let mut head = table(b"head")?;
+    let long_offsets = u16::from_be_bytes([head[50], head[51]]) == 1;
+
+    let glyph_data = |id: u16| -> &[u8] {
+        let offset = |index: usize| match long_offsets {
+            true => read_u32(&loca, index * 4) as usize,
+            false => read_u16(&loca, index * 2) as usize * 2,
+        };
+        let (start, end) = (offset(id as usize), offset(id as usize + 1));
+        glyf.get(start..end).unwrap_or_default()
+    };
+
+    // The notdef glyph is always kept, and composite glyphs keep their components
+    let mut kept: BTreeSet<u16> = BTreeSet::new();
+    let mut pending: Vec<u16> = std::iter::once(0).chain(glyphs.iter().copied()).collect();
+    while let Some(id) = pending.pop() {
+        if id >= face.number_of_glyphs() || !kept.insert(id) {
+            continue;
+        }
+        pending.extend(components(glyph_data(id)));
+    }
+
+    let mut new_glyf: Vec<u8> = Vec::new();
+    let mut new_loca: Vec<u8> = Vec::new();
+    for id in 0..face.number_of_glyphs() {
+        new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+        if kept.contains(&id) {
+            new_glyf.extend(glyph_data(id));
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+
+    // Long offsets in `loca`, the checksum adjustment is set when the font is written
+    head[8..12].copy_from_slice(&[0; 4]);
+    head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = vec![
+        (*b"glyf", new_glyf),
+        (*b"loca", new_loca),
+        (*b"head", head),
+        (*b"name", subset_name(&face, &subset_tag(glyphs))),
+    ];
+
+    // Version 3 of `post` has no glyph names
+    if let Some(mut post) = table(b"post") {
+        post.truncate(32);
+        post[0..4].copy_from_slice(&0x00030000u32.to_be_bytes());
+        tables.push((*b"post", post));
+    }
+
+    for record in raw_face.table_records {
+        let tag = record.tag.to_bytes();
+        if !DROPPED_TABLES.contains(&&tag) && !REBUILT_TABLES.contains(&&tag) {
+            tables.push((tag, table(&tag)?));
+        }
+    }
+
+    Some(variable_font::write_font(tables))
+}
+
+/// Six uppercase letters that identify a subset, PDF readers expect the names of
+/// subset fonts to start with such a tag followed by a plus sign.
+pub fn subset_tag(glyphs: &BTreeSet<u16>) -> String {
+    // FNV-1a, so that the same glyphs are always given the same tag
+    let mut hash = glyphs
+        .iter()
+        .flat_map(|id| id.to_be_bytes())
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+    (0..6)
+        .map(|_| {
+            let letter = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            letter
+        })
+        .collect()
+}
+
+// Glyphs referred to by a composite glyph
+fn components(data: &[u8]) -> Vec<u16> {
+    let mut components = Vec::new();
+    if data.len() < 10 || i16::from_be_bytes([data[0], data[1]]) >= 0 {
+        return components;
+    }
+
+    let mut offset = 10;
+    while offset + 4 <= data.len() {
+        let flags = read_u16(data, offset);
+        components.push(read_u16(data, offset + 2));
+
+        offset += 4;
+        offset += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+
+    components
+}
+
+// The `name` table with the subset tag in front of the PostScript name
+fn subset_name(face: &Face, tag: &str) -> Vec<u8> {
+    let names = face
+        .names()
+        .into_iter()
+        .map(|name| {
+            let mut string = name.name.to_vec();
+            if name.name_id == name_id::POST_SCRIPT_NAME {
+                // Mac names are single byte, the other platforms use UTF-16BE
+                let prefix = match name.platform_id {
+                    PlatformId::Macintosh => format!("{}+", tag).into_bytes(),
+                    _ => format!("{}+", tag)
+                        .encode_utf16()
+                        .flat_map(|c| c.to_be_bytes())
+                        .collect(),
+                };
+                string = [prefix, string].concat();
+            }
+            (name, string)
+        })
+        .collect::<Vec<_>>();
+
+    let mut records: Vec<u8> = Vec::new();
+    let mut strings: Vec<u8> = Vec::new();
+    for (name, string) in names.iter() {
+        records.extend((name.platform_id as u16).to_be_bytes());
+        records.extend(name.encoding_id.to_be_bytes());
+        records.extend(name.language_id.to_be_bytes());
+        records.extend(name.name_id.to_be_bytes());
+        records.extend((string.len() as u16).to_be_bytes());
+        records.extend((strings.len() as u16).to_be_bytes());
+        strings.extend(string);
+    }
+
+    let mut table: Vec<u8> = Vec::new();
+    table.extend(0u16.to_be_bytes());
+    table.extend((names.len() as u16).to_be_bytes());
+    table.extend((6 + records.len() as u16).to_be_bytes());
+    table.extend(records);
+    table.extend(strings);
+    table
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ttf_parser::GlyphId;
+
+    #[test]
+    fn test_subset() {
+        let data = std::fs::read("assets/Exo/static/Exo-Medium.ttf").unwrap();
+        let face = Face::parse(&data, 0).unwrap();
+        let glyphs = "Hié"
+            .chars()
+            .map(|c| face.glyph_index(c).unwrap().0)
+            .collect::<BTreeSet<u16>>();
+
+        let subset_data = subset(&data, &glyphs).unwrap();
+        assert!(subset_data.len() < data.len() / 2);
+
+        // Glyph ids and advances are kept, only the used outlines are
+        let subset = Face::parse(&subset_data, 0).unwrap();
+        assert_eq!(subset.number_of_glyphs(), face.number_of_glyphs());
+        for c in "Hié".chars() {
+            let id = face.glyph_index(c).unwrap();
+            assert_eq!(subset.glyph_index(c), Some(id));
+            assert_eq!(subset.glyph_bounding_box(id), face.glyph_bounding_box(id));
+            assert_eq!(subset.glyph_hor_advance(id), face.glyph_hor_advance(id));
+        }
+        let w = face.glyph_index('W').unwrap();
+        assert!(subset.glyph_bounding_box(w).is_none());
+        assert!(subset.glyph_bounding_box(GlyphId(0)).is_some());
+
+        let post_script_name = subset
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .unwrap();
+        assert_eq!(
+            post_script_name,
+            format!("{}+Exo-Medium", subset_tag(&glyphs))
+        );
+        assert_eq!(subset_tag(&glyphs).len(), 6);
+        assert!(subset_tag(&glyphs).chars().all(|c| c.is_ascii_uppercase()));
+
+        assert!(rusttype::Font::try_from_vec(subset_data).is_some());
+    }
+}
@@ -0,0 +1,435 @@
+use std::str::FromStr;
+
+use serde::{de::Visitor, Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    font::{Font, FontDict},
+    layout::SectionLayout,
+    width::{Length, Width},
+};
+
+/// The width of a column of a grid, written as "72pt", "25%", "1fr" or "auto".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Track {
+    Absolute(f32),
+    // A percentage of the width of the grid
+    Percentage(f32),
+    // A share of the width left over by the other columns
+    Fr(f32),
+    // As wide as the widest cell of the column
+    Auto,
+}
+
+impl FromStr for Track {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Track, Self::Err> {
+        let s = s.trim();
+        if s == "auto" {
+            return Ok(Track::Auto);
+        }
+        if let Some(stripped) = s.strip_suffix("fr") {
+            return stripped
+                .trim()
+                .parse::<f32>()
+                .map(Track::Fr)
+                .map_err(|_| format!("invalid column track: {}", s));
+        }
+
+        match s.parse::<Length>()? {
+            Length::Absolute(w) => Ok(Track::Absolute(w)),
+            Length::Percentage(w) => Ok(Track::Percentage(w)),
+            Length::Em(_) | Length::Fill => Err(format!("invalid column track: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Track {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Track::Absolute(w) => write!(f, "{}", Length::Absolute(*w)),
+            Track::Percentage(w) => write!(f, "{}", Length::Percentage(*w)),
+            Track::Fr(fr) => write!(f, "{}fr", fr),
+            Track::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for Track {
+    type Value = Track;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a string representing a column track")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse::<Track>().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Track {
+    fn deserialize<D>(deserializer: D) -> Result<Track, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(Track::Auto)
+    }
+}
+
+impl Serialize for Track {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GridCell {
+    pub element: SectionLayout,
+    #[serde(default = "GridCell::default_span")]
+    pub column_span: usize,
+    #[serde(default = "GridCell::default_span")]
+    pub row_span: usize,
+}
+
+impl GridCell {
+    pub fn default_span() -> usize {
+        1
+    }
+
+    pub fn with_element(&self, element: SectionLayout) -> GridCell {
+        GridCell {
+            element,
+            column_span: self.column_span,
+            row_span: self.row_span,
+        }
+    }
+}
+
+/// Lays out its cells in columns, filling the rows from left to right. Unlike rows, the
+/// columns of a grid line up across the items of a section, auto columns are as wide as
+/// their widest cell in any of the items.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Grid {
+    #[serde(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uid: Uuid,
+    pub columns: Vec<Track>,
+    pub cells: Vec<GridCell>,
+    #[serde(default = "Width::default")]
+    pub width: Width,
+    #[serde(default = "f32::default")]
+    pub column_gap: f32,
+    #[serde(default = "f32::default")]
+    pub row_gap: f32,
+    // Sizes the auto columns by the cells of every item of the section, not only this one
+    #[serde(default = "Grid::default_align_section")]
+    pub align_section: bool,
+    // Widths of the cells of the auto columns across the section
+    #[serde(skip)]
+    pub section_widths: Vec<f32>,
+}
+
+impl Grid {
+    pub fn default_align_section() -> bool {
+        true
+    }
+
+    /// Applies `f` to the elements of the cells.
+    pub fn map(&self, mut f: impl FnMut(&SectionLayout) -> SectionLayout) -> Grid {
+        Grid {
+            uid: self.uid,
+            columns: self.columns.clone(),
+            cells: self
+                .cells
+                .iter()
+                .map(|cell| cell.with_element(f(&cell.element)))
+                .collect(),
+            width: self.width,
+            column_gap: self.column_gap,
+            row_gap: self.row_gap,
+            align_section: self.align_section,
+            section_widths: self.section_widths.clone(),
+        }
+    }
+
+    pub fn with_width(&self, width: Width) -> Grid {
+        Grid {
+            uid: self.uid,
+            columns: self.columns.clone(),
+            cells: self.cells.clone(),
+            width,
+            column_gap: self.column_gap,
+            row_gap: self.row_gap,
+            align_section: self.align_section,
+            section_widths: self.section_widths.clone(),
+        }
+    }
+
+    pub fn with_section_widths(&self, section_widths: Vec<f32>) -> Grid {
+        Grid {
+            uid: self.uid,
+            columns: self.columns.clone(),
+            cells: self.cells.clone(),
+            width: self.width,
+            column_gap: self.column_gap,
+            row_gap: self.row_gap,
+            align_section: self.align_section,
+            section_widths,
+        }
+    }
+
+    fn column_count(&self) -> usize {
+        self.columns.len().max(1)
+    }
+
+    // Grids without columns have a single one taking the whole width
+    fn track(&self, column: usize) -> Track {
+        self.columns.get(column).copied().unwrap_or(Track::Fr(1.0))
+    }
+
+    fn column_span(&self, cell: &GridCell) -> usize {
+        cell.column_span.clamp(1, self.column_count())
+    }
+
+    /// The row and the column of each cell. Cells take the first place after the previous
+    /// cell where they fit, skipping the places taken by cells spanning several rows.
+    pub fn placements(&self) -> Vec<(usize, usize)> {
+        let columns = self.column_count();
+        let mut taken: Vec<Vec<bool>> = Vec::new();
+        let (mut row, mut column) = (0, 0);
+
+        self.cells
+            .iter()
+            .map(|cell| {
+                let (column_span, row_span) = (self.column_span(cell), cell.row_span.max(1));
+                loop {
+                    if column + column_span > columns {
+                        (row, column) = (row + 1, 0);
+                        continue;
+                    }
+                    if taken.len() < row + row_span {
+                        taken.resize(row + row_span, vec![false; columns]);
+                    }
+                    let free = taken[row..row + row_span]
+                        .iter()
+                        .all(|r| r[column..column + column_span].iter().all(|t| !t));
+                    if free {
+                        break;
+                    }
+                    column += 1;
+                }
+
+                for r in &mut taken[row..row + row_span] {
+                    r[column..column + column_span].fill(true);
+                }
+                let placement = (row, column);
+                column += column_span;
+                placement
+            })
+            .collect()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.placements()
+            .iter()
+            .zip(&self.cells)
+            .map(|((row, _), cell)| row + cell.row_span.max(1))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The widest cell of each auto column, cells spanning several columns are left out.
+    pub fn auto_widths(&self, font_dict: &FontDict) -> Vec<f32> {
+        let mut widths = vec![0.0; self.column_count()];
+        for (cell, (_, column)) in self.cells.iter().zip(self.placements()) {
+            if self.column_span(cell) == 1 && self.track(column) == Track::Auto {
+                widths[column] = f32::max(widths[column], cell.element.content_width(font_dict));
+            }
+        }
+        widths
+    }
+
+    fn gaps(&self) -> f32 {
+        (self.column_count() - 1) as f32 * self.column_gap
+    }
+
+    /// Widths of the columns of a grid of `width`. Fixed columns are placed first, auto
+    /// columns shrink when they do not fit next to them and fr columns share what is left.
+    fn resolve_columns(&self, width: f32, font_dict: &FontDict) -> Vec<f32> {
+        let available = f32::max(width - self.gaps(), 0.0);
+        let auto_widths = self.auto_widths(font_dict);
+
+        let mut widths = (0..self.column_count())
+            .map(|i| match self.track(i) {
+                Track::Absolute(w) => w,
+                Track::Percentage(w) => w / 100.0 * width,
+                Track::Fr(_) => 0.0,
+                Track::Auto => f32::max(
+                    auto_widths[i],
+                    self.section_widths.get(i).copied().unwrap_or(0.0),
+                ),
+            })
+            .collect::<Vec<f32>>();
+
+        let is_auto = |i: usize| self.track(i) == Track::Auto;
+        let fixed: f32 = (0..widths.len())
+            .filter(|i| !is_auto(*i))
+            .map(|i| widths[i])
+            .sum();
+        let auto: f32 = (0..widths.len())
+            .filter(|i| is_auto(*i))
+            .map(|i| widths[i])
+            .sum();
+        let room = f32::max(available - fixed, 0.0);
+        if auto > room {
+            for i in (0..widths.len()).filter(|i| is_auto(*i)) {
+                widths[i] *= room / auto;
+            }
+        }
+
+        let fr_total: f32 = (0..widths.len())
+            .map(|i| match self.track(i) {
+                Track::Fr(fr) => fr,
+                _ => 0.0,
+            })
+            .sum();
+        let left = f32::max(available - widths.iter().sum::<f32>(), 0.0);
+        for (i, width) in widths.iter_mut().enumerate() {
+            if let Track::Fr(fr) = self.track(i) {
+                *width = left * fr / fr_total;
+            }
+        }
+
+        widths
+    }
+
+    /// Widths of the columns once they are resolved by `fill_fonts`.
+    pub fn column_widths(&self) -> Vec<f32> {
+        self.columns
+            .iter()
+            .map(|track| match track {
+                Track::Absolute(w) => *w,
+                _ => unreachable!("Cannot lay out the unresolved column {}", track),
+            })
+            .collect()
+    }
+
+    // Left of `column` and the width of `span` columns from it, gaps included
+    pub fn span(&self, widths: &[f32], column: usize, span: usize) -> (f32, f32) {
+        let left = widths[..column].iter().sum::<f32>() + column as f32 * self.column_gap;
+        let width =
+            widths[column..column + span].iter().sum::<f32>() + (span - 1) as f32 * self.column_gap;
+        (left, width)
+    }
+
+    /// The width the grid takes when it is not stretched, the fixed columns and the widest
+    /// cells of the auto ones.
+    pub fn content_width(&self, font_dict: &FontDict) -> f32 {
+        let auto_widths = self.auto_widths(font_dict);
+        (0..self.column_count())
+            .map(|i| match self.track(i) {
+                Track::Absolute(w) => w,
+                Track::Auto => auto_widths[i],
+                _ => 0.0,
+            })
+            .sum::<f32>()
+            + self.gaps()
+    }
+
+    pub fn fonts(&self) -> Vec<Font> {
+        self.cells.iter().flat_map(|c| c.element.fonts()).collect()
+    }
+
+    pub fn is_instantiated(&self) -> bool {
+        self.cells.iter().all(|c| c.element.is_instantiated())
+    }
+
+    // The cells are bounded to their columns once fonts are filled
+    pub fn bound_width(&self, width: f32) -> Grid {
+        self.with_width(Width::Absolute(
+            self.width.bound(width).get_fixed_unchecked(),
+        ))
+    }
+
+    pub fn scale_width(&self, w: f32) -> Grid {
+        // Grids have no font of their own, so ems are relative to the default font size
+        self.map(|e| e.scale_width(w))
+            .with_width(self.width.scale(w, Font::default_size()))
+    }
+
+    /// Resolves the widths of the columns, which depend on the text of the auto columns,
+    /// and fits the cells into them.
+    pub fn fill_fonts(&self, font_dict: &FontDict) -> Grid {
+        let widths = self.resolve_columns(self.width.get_fixed_unchecked(), font_dict);
+        let placements = self.placements();
+
+        Grid {
+            columns: widths.iter().map(|w| Track::Absolute(*w)).collect(),
+            cells: self
+                .cells
+                .iter()
+                .zip(placements)
+                .map(|(cell, (_, column))| {
+                    let (_, width) = self.span(&widths, column, self.column_span(cell));
+                    cell.with_element(cell.element.bound_width(width).fill_fonts(font_dict))
+                })
+                .collect(),
+            uid: self.uid,
+            width: self.width,
+            column_gap: self.column_gap,
+            row_gap: self.row_gap,
+            align_section: self.align_section,
+            section_widths: self.section_widths.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placements() {
+        let tracks: Vec<Track> =
+            serde_json::from_str(r#"["72pt", "25%", "1.5fr", "auto"]"#).unwrap();
+        assert_eq!(
+            tracks,
+            vec![
+                Track::Absolute(72.0),
+                Track::Percentage(25.0),
+                Track::Fr(1.5),
+                Track::Auto,
+            ]
+        );
+        assert!(serde_json::from_str::<Track>(r#""fill""#).is_err());
+
+        let cell = |column_span: usize, row_span: usize| {
+            format!(
+                r#"{{ "element": {{ "Rule": {{}} }}, "column_span": {}, "row_span": {} }}"#,
+                column_span, row_span
+            )
+        };
+        // A cell spanning both rows of the first column, and one too wide for the grid
+        let json = format!(
+            r#"{{ "columns": ["auto", "1fr", "1fr"], "cells": [{}, {}, {}, {}, {}] }}"#,
+            cell(1, 2),
+            cell(2, 1),
+            cell(1, 1),
+            cell(1, 1),
+            cell(5, 1)
+        );
+        let grid: Grid = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            grid.placements(),
+            vec![(0, 0), (0, 1), (1, 1), (1, 2), (2, 0)]
+        );
+        assert_eq!(grid.row_count(), 3);
+    }
+}
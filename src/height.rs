@@ -0,0 +1,115 @@
+use std::str::FromStr;
+
+use serde::{de::Visitor, Deserialize, Serialize};
+
+use crate::width::Length;
+
+/// Height of a container. By default, containers are as high as their contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Height {
+    #[default]
+    Auto,
+    Absolute(f32),
+    // At least this high, written as "auto(min=40pt)"
+    Min(f32),
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Height, Self::Err> {
+        let s = s.trim();
+        let absolute = |v: &str| match v.parse::<Length>()? {
+            Length::Absolute(h) => Ok(h),
+            _ => Err(format!("heights must be absolute lengths: {}", s)),
+        };
+
+        if s == "auto" {
+            Ok(Height::Auto)
+        } else if let Some(min) = s.strip_prefix("auto(").and_then(|s| s.strip_suffix(')')) {
+            let min = min
+                .trim()
+                .strip_prefix("min")
+                .and_then(|m| m.trim().strip_prefix('='))
+                .ok_or_else(|| format!("invalid height: {}", s))?;
+            Ok(Height::Min(absolute(min)?))
+        } else {
+            Ok(Height::Absolute(absolute(s)?))
+        }
+    }
+}
+
+impl std::fmt::Display for Height {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Height::Auto => write!(f, "auto"),
+            Height::Absolute(h) => write!(f, "{}pt", h),
+            Height::Min(h) => write!(f, "auto(min={}pt)", h),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for Height {
+    type Value = Height;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a string representing a height")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse::<Height>().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Height {
+    fn deserialize<D>(deserializer: D) -> Result<Height, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(Height::Auto)
+    }
+}
+
+impl Serialize for Height {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Height {
+    /// Computes the final height of a container whose contents are `content_height` high.
+    pub fn resolve(&self, content_height: f32) -> f32 {
+        match self {
+            Height::Auto => content_height,
+            Height::Absolute(h) => *h,
+            Height::Min(h) => f32::max(*h, content_height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let heights: Vec<Height> =
+            serde_json::from_str(r#"["auto", "20pt", "1in", "auto(min=10mm)"]"#).unwrap();
+        assert_eq!(heights[0], Height::Auto);
+        assert_eq!(heights[1], Height::Absolute(20.0));
+        assert_eq!(heights[2], Height::Absolute(72.0));
+        assert!(matches!(heights[3], Height::Min(h) if (h - 28.346).abs() < 0.001));
+
+        assert!(serde_json::from_str::<Height>(r#""50%""#).is_err());
+
+        assert_eq!(Height::Min(10.0).resolve(5.0), 10.0);
+        assert_eq!(Height::Min(10.0).resolve(15.0), 15.0);
+        assert_eq!(Height::Absolute(10.0).resolve(15.0), 10.0);
+    }
+}
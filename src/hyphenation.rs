@@ -0,0 +1,82 @@
+use hypher::Lang;
+use serde::{Deserialize, Serialize};
+
+/// Hyphenation settings of a paragraph. The patterns of every supported language
+/// are bundled with `hypher`, so no dictionaries have to be installed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hyphenation {
+    // ISO 639-1 code of the language of the text, such as "en" or "de"
+    #[serde(default = "Hyphenation::default_language")]
+    pub language: String,
+    // Words with fewer letters than this are never hyphenated
+    #[serde(default = "Hyphenation::default_min_word_length")]
+    pub min_word_length: usize,
+    // Maximum number of consecutive lines that may end with a hyphen
+    #[serde(default = "Hyphenation::default_max_consecutive_hyphens")]
+    pub max_consecutive_hyphens: usize,
+}
+
+impl Default for Hyphenation {
+    fn default() -> Hyphenation {
+        Hyphenation {
+            language: Hyphenation::default_language(),
+            min_word_length: Hyphenation::default_min_word_length(),
+            max_consecutive_hyphens: Hyphenation::default_max_consecutive_hyphens(),
+        }
+    }
+}
+
+impl Hyphenation {
+    pub fn default_language() -> String {
+        "en".to_string()
+    }
+
+    pub fn default_min_word_length() -> usize {
+        5
+    }
+
+    pub fn default_max_consecutive_hyphens() -> usize {
+        2
+    }
+
+    fn lang(&self) -> Option<Lang> {
+        let code: [u8; 2] = self.language.to_lowercase().as_bytes().try_into().ok()?;
+        Lang::from_iso(code)
+    }
+
+    /// Splits `word` into the syllables it can be hyphenated between. Words that are too
+    /// short, or that are in a language without patterns, are returned as a single syllable.
+    pub fn syllables<'a>(&self, word: &'a str) -> Vec<&'a str> {
+        let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+        match self.lang() {
+            Some(lang) if letters >= self.min_word_length => {
+                hypher::hyphenate(word, lang).collect()
+            }
+            Some(_) => vec![word],
+            None => {
+                log::warn!("No hyphenation patterns for language {}", self.language);
+                vec![word]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables() {
+        let hyphenation = Hyphenation::default();
+        assert_eq!(
+            hyphenation.syllables("extensive"),
+            vec!["ex", "ten", "sive"]
+        );
+        assert_eq!(hyphenation.syllables("word"), vec!["word"]);
+
+        let hyphenation: Hyphenation =
+            serde_json::from_str(r#"{ "language": "xx", "min_word_length": 3 }"#).unwrap();
+        assert_eq!(hyphenation.max_consecutive_hyphens, 2);
+        assert_eq!(hyphenation.syllables("extensive"), vec!["extensive"]);
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    color::Color,
+    font::{Font, PartialFont},
+    image_element,
+    resume_data::ItemContent,
+    shape::Shape,
+    spatial_box::SpatialBox,
+    style::Style,
+};
+
+/// The icons bundled with the library, drawn in the color of the text they accompany.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum IconName {
+    Email,
+    Phone,
+    Location,
+    Website,
+    Link,
+    // Source code hosts, such as GitHub and GitLab
+    Code,
+    Linkedin,
+}
+
+impl IconName {
+    fn svg(&self) -> &'static str {
+        match self {
+            IconName::Email => include_str!("../assets/icons/email.svg"),
+            IconName::Phone => include_str!("../assets/icons/phone.svg"),
+            IconName::Location => include_str!("../assets/icons/location.svg"),
+            IconName::Website => include_str!("../assets/icons/website.svg"),
+            IconName::Link => include_str!("../assets/icons/link.svg"),
+            IconName::Code => include_str!("../assets/icons/code.svg"),
+            IconName::Linkedin => include_str!("../assets/icons/linkedin.svg"),
+        }
+    }
+
+    /// The icon for a link, picked by its scheme and for web links by its host. Bare
+    /// email addresses and phone numbers are recognized without a scheme.
+    pub fn from_url(url: &str) -> IconName {
+        let url = url.trim().to_lowercase();
+        if IconName::is_email(&url) {
+            return IconName::Email;
+        }
+        if IconName::is_phone(&url) {
+            return IconName::Phone;
+        }
+        let Some((scheme, rest)) = url.split_once(':') else {
+            return IconName::Link;
+        };
+
+        match scheme {
+            "mailto" => IconName::Email,
+            "tel" => IconName::Phone,
+            "geo" => IconName::Location,
+            "http" | "https" => {
+                let host = rest
+                    .trim_start_matches('/')
+                    .split(['/', '?', '#', ':'])
+                    .next()
+                    .unwrap_or_default();
+                let host = host.strip_prefix("www.").unwrap_or(host);
+                match host {
+                    "github.com" | "gitlab.com" | "bitbucket.org" | "codeberg.org" => {
+                        IconName::Code
+                    }
+                    "linkedin.com" => IconName::Linkedin,
+                    _ => IconName::Website,
+                }
+            }
+            _ => IconName::Link,
+        }
+    }
+
+    // A single `@` between a local part and a domain with a dot, such as jane@example.com
+    fn is_email(text: &str) -> bool {
+        let Some((local, domain)) = text.split_once('@') else {
+            return false;
+        };
+        !local.is_empty()
+            && !local.contains([':', '/'])
+            && domain
+                .split_once('.')
+                .is_some_and(|(name, tld)| !name.is_empty() && !tld.is_empty())
+            && !domain.contains(['@', '/', ':'])
+            && !text.contains(char::is_whitespace)
+    }
+
+    // Digits with the usual separators, such as +1 (555) 010-0100
+    fn is_phone(text: &str) -> bool {
+        let digits = text.chars().filter(char::is_ascii_digit).count();
+        digits >= 5
+            && text
+                .trim_start_matches('+')
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')' | '/'))
+    }
+
+    /// Rasterizes the icon in `color`, contained in `box_` at `scale` pixels per point.
+    pub fn rasterize(
+        &self,
+        color: Color,
+        box_: &SpatialBox,
+        scale: f32,
+    ) -> Result<(SpatialBox, RgbaImage), String> {
+        let opaque = Color::rgb(color.r, color.g, color.b);
+        let svg = self.svg().replace("currentColor", &opaque.to_string());
+        let (drawn, mut pixels) = image_element::fit_svg(svg.as_bytes(), box_, scale)?;
+
+        for pixel in pixels.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * color.alpha()).round() as u8;
+        }
+        Ok((drawn, pixels))
+    }
+}
+
+/// Where the name of an icon comes from. `Ref`s are fields of the section, their icons are
+/// picked by the link they hold when the layout is instantiated.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum IconSource {
+    Name(IconName),
+    Ref(String),
+}
+
+/// A small square icon sized by its font, sitting on the baseline of the font like text.
+/// The font cascades from the containers above the icon like the font of text, so an icon
+/// in a row of text is sized and aligned like the text around it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Icon {
+    #[serde(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uid: Uuid,
+    pub source: IconSource,
+    #[serde(default = "PartialFont::default")]
+    pub font: PartialFont,
+    // Size of the icon relative to the size of the font
+    #[serde(default = "Icon::default_scale")]
+    pub scale: f32,
+    // Color of the icon, falls back to the color of the font
+    #[serde(default = "Option::default")]
+    pub color: Option<Color>,
+    // Name of the style the font and the color of the icon come from
+    #[serde(default = "Option::default")]
+    pub style: Option<String>,
+}
+
+impl Icon {
+    pub fn default_scale() -> f32 {
+        0.8
+    }
+
+    pub fn font(&self) -> Font {
+        self.font.resolve()
+    }
+
+    pub fn size(&self) -> f32 {
+        self.font().size * self.scale
+    }
+
+    pub fn is_instantiated(&self) -> bool {
+        matches!(self.source, IconSource::Name(_))
+    }
+
+    // Icons referring to a missing field are left out, like text referring to one
+    pub fn instantiate(&self, section: &HashMap<String, ItemContent>) -> Option<Icon> {
+        let name = match &self.source {
+            IconSource::Name(name) => *name,
+            IconSource::Ref(field) => match section.get(field)? {
+                ItemContent::Url { url, text: _ } => IconName::from_url(url),
+                content => IconName::from_url(&content.to_string()),
+            },
+        };
+
+        Some(Icon {
+            uid: self.uid,
+            source: IconSource::Name(name),
+            font: self.font.clone(),
+            scale: self.scale,
+            color: self.color,
+            style: self.style.clone(),
+        })
+    }
+
+    // The properties the icon does not set itself are taken from `style`
+    pub fn with_style(&self, style: &Style) -> Icon {
+        Icon {
+            uid: self.uid,
+            source: self.source.clone(),
+            font: self.font.over(&style.font),
+            scale: self.scale,
+            color: self.color.or(style.color),
+            style: self.style.clone(),
+        }
+    }
+
+    pub fn shape(&self) -> Shape {
+        let IconSource::Name(name) = self.source else {
+            unreachable!("Cannot draw the icon of an uninstantiated layout")
+        };
+        Shape::Icon {
+            name,
+            color: self.color.unwrap_or(self.font().color),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{layout::SectionLayout, layout_schema::LayoutSchema, style::Styles};
+
+    #[test]
+    fn test_from_url() {
+        assert_eq!(
+            IconName::from_url("mailto:jane@example.com"),
+            IconName::Email
+        );
+        assert_eq!(IconName::from_url("tel:+1-555-0100"), IconName::Phone);
+        assert_eq!(
+            IconName::from_url("https://github.com/jane"),
+            IconName::Code
+        );
+        assert_eq!(
+            IconName::from_url("HTTPS://www.LinkedIn.com/in/jane"),
+            IconName::Linkedin
+        );
+        assert_eq!(
+            IconName::from_url("https://jane.dev:8080/"),
+            IconName::Website
+        );
+        assert_eq!(IconName::from_url("jane.dev"), IconName::Link);
+        assert_eq!(
+            IconName::from_url(" Jane.Doe@example.com "),
+            IconName::Email
+        );
+        assert_eq!(IconName::from_url("+1 (555) 010-0100"), IconName::Phone);
+        assert_eq!(IconName::from_url("555.010.0100"), IconName::Phone);
+        assert_eq!(IconName::from_url("@jane"), IconName::Link);
+        assert_eq!(IconName::from_url("2024"), IconName::Link);
+
+        let icon: Icon = serde_json::from_str(r#"{ "source": { "Ref": "Email" } }"#).unwrap();
+        let section = [(
+            "Email".to_string(),
+            ItemContent::Url {
+                url: "mailto:jane@example.com".to_string(),
+                text: "jane@example.com".to_string(),
+            },
+        )]
+        .into_iter()
+        .collect();
+        let icon = icon.instantiate(&section).unwrap();
+        assert_eq!(icon.source, IconSource::Name(IconName::Email));
+        assert_eq!(icon.size(), 9.6);
+        assert!(icon.instantiate(&HashMap::new()).is_some());
+        assert!(Icon {
+            source: IconSource::Ref("Phone".to_string()),
+            ..icon
+        }
+        .instantiate(&HashMap::new())
+        .is_none());
+    }
+
+    #[test]
+    fn test_font_cascade() {
+        let json = r##"[{
+            "schema_name": "Contact",
+            "styles": {
+                "contact": { "font": { "name": "Exo", "size": 10.0 }, "color": "#1F6FEB" }
+            },
+            "header_layout_schema": { "Text": { "item": "Contact" } },
+            "item_layout_schema": {
+                "FlexRow": {
+                    "style": "contact",
+                    "elements": [
+                        { "Icon": { "source": { "Ref": "Email" } } },
+                        { "Ref": { "item": "Email" } },
+                        { "Icon": { "source": { "Name": "Phone" }, "font": { "size": 20.0 } } }
+                    ]
+                }
+            }
+        }]"##;
+        let schema = LayoutSchema::from_json(json)[0].resolve_styles(&Styles::new());
+        let SectionLayout::FlexRow(row) = &schema.item_layout_schema else {
+            panic!("the item layout is not a row");
+        };
+        let (SectionLayout::Icon(email), SectionLayout::Ref(text), SectionLayout::Icon(phone)) =
+            (&row.elements[0], &row.elements[1], &row.elements[2])
+        else {
+            panic!("the row is not an icon, a ref and an icon");
+        };
+
+        // Icons take the font of the text around them, so they share its size and baseline
+        assert_eq!(email.font().name, text.font().name);
+        assert_eq!(email.font().size, text.font().size);
+        assert_eq!(email.size(), 8.0);
+        assert_eq!(email.color.unwrap().b, 0xEB);
+        // Their own font still wins over the cascading one
+        assert_eq!(phone.font().name, "Exo");
+        assert_eq!(phone.size(), 16.0);
+    }
+}
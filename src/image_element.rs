@@ -0,0 +1,303 @@
+use std::{collections::HashMap, path::Path};
+
+use image::{imageops, Rgba, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    font::Font, point::Point, resume_data::ItemContent, spatial_box::SpatialBox, width::Width,
+};
+
+/// How an image is scaled into its box.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum ImageFit {
+    // The whole image is shown, centered in the box
+    #[default]
+    Contain,
+    // The image covers the box, the overflowing edges are cropped
+    Cover,
+}
+
+/// Where an image is read from. Paths are relative to the storage directory, `Ref`s are
+/// the names of `Image` fields of the section, replaced by their paths when the layout is
+/// instantiated.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ImageSource {
+    Path(String),
+    Ref(String),
+}
+
+/// A JPEG, PNG or SVG image, such as a photo or a logo.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageElement {
+    #[serde(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uid: Uuid,
+    pub source: ImageSource,
+    #[serde(default = "Width::default")]
+    pub width: Width,
+    pub height: f32,
+    #[serde(default = "ImageFit::default")]
+    pub fit: ImageFit,
+    // Clips the image to a circle, or to a pill for boxes that are not square
+    #[serde(default = "bool::default")]
+    pub circular: bool,
+}
+
+// A decoded image, SVGs are rasterized at the size they are drawn at
+enum Decoded {
+    Raster(RgbaImage),
+    Svg(Box<usvg::Tree>),
+}
+
+impl Decoded {
+    fn read(path: &Path) -> Result<Decoded, String> {
+        let is_svg = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+
+        if is_svg {
+            let data = std::fs::read(path).map_err(|e| e.to_string())?;
+            usvg::Tree::from_data(&data, &usvg::Options::default())
+                .map(|tree| Decoded::Svg(Box::new(tree)))
+                .map_err(|e| e.to_string())
+        } else {
+            image::open(path)
+                .map(|image| Decoded::Raster(image.to_rgba8()))
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    fn size(&self) -> (f32, f32) {
+        match self {
+            Decoded::Raster(image) => (image.width() as f32, image.height() as f32),
+            Decoded::Svg(tree) => (tree.size().width(), tree.size().height()),
+        }
+    }
+
+    fn rasterize(&self, width: u32, height: u32) -> RgbaImage {
+        match self {
+            Decoded::Raster(image) => {
+                imageops::resize(image, width, height, imageops::FilterType::CatmullRom)
+            }
+            Decoded::Svg(tree) => {
+                let mut pixmap = tiny_skia::Pixmap::new(width, height).unwrap();
+                let (svg_width, svg_height) = self.size();
+                let transform = tiny_skia::Transform::from_scale(
+                    width as f32 / svg_width,
+                    height as f32 / svg_height,
+                );
+                resvg::render(tree, transform, &mut pixmap.as_mut());
+
+                // tiny-skia stores premultiplied colors
+                RgbaImage::from_fn(width, height, |x, y| {
+                    let offset = ((y * width + x) * 4) as usize;
+                    let pixel = &pixmap.data()[offset..offset + 4];
+                    let channel = |c: u8| match pixel[3] {
+                        0 => 0,
+                        a => (c as u32 * 255 / a as u32) as u8,
+                    };
+                    Rgba([
+                        channel(pixel[0]),
+                        channel(pixel[1]),
+                        channel(pixel[2]),
+                        pixel[3],
+                    ])
+                })
+            }
+        }
+    }
+}
+
+impl ImageElement {
+    pub fn is_instantiated(&self) -> bool {
+        matches!(self.source, ImageSource::Path(_))
+    }
+
+    // Images referring to a missing field are left out, like text referring to one
+    pub fn instantiate(&self, section: &HashMap<String, ItemContent>) -> Option<ImageElement> {
+        match &self.source {
+            ImageSource::Path(_) => Some(self.clone()),
+            ImageSource::Ref(field) => section
+                .get(field)
+                .map(|path| self.with_source(ImageSource::Path(path.to_string()))),
+        }
+    }
+
+    pub fn with_source(&self, source: ImageSource) -> ImageElement {
+        ImageElement {
+            uid: self.uid,
+            source,
+            width: self.width,
+            height: self.height,
+            fit: self.fit,
+            circular: self.circular,
+        }
+    }
+
+    pub fn with_width(&self, width: Width) -> ImageElement {
+        ImageElement {
+            uid: self.uid,
+            source: self.source.clone(),
+            width,
+            height: self.height,
+            fit: self.fit,
+            circular: self.circular,
+        }
+    }
+
+    pub fn bound_width(&self, width: f32) -> ImageElement {
+        self.with_width(Width::Absolute(
+            self.width.bound(width).get_fixed_unchecked(),
+        ))
+    }
+
+    pub fn scale_width(&self, w: f32) -> ImageElement {
+        // Images have no font, so ems are relative to the default font size
+        self.with_width(self.width.scale(w, Font::default_size()))
+    }
+
+    // Radius of the corners of the clip, large enough to round the shorter side fully
+    pub fn clip_radius(&self, box_: &SpatialBox) -> Option<f32> {
+        self.circular.then(|| box_.width().min(box_.height()) / 2.0)
+    }
+
+    /// Reads the image from `dir` and fits it into `box_`. Returns the box the image is
+    /// drawn in, which is smaller than `box_` for contained images of another aspect ratio,
+    /// and its pixels at `scale` pixels per point.
+    pub fn fit_into(
+        &self,
+        dir: &Path,
+        box_: &SpatialBox,
+        scale: f32,
+    ) -> Result<(SpatialBox, RgbaImage), String> {
+        let ImageSource::Path(path) = &self.source else {
+            unreachable!("Cannot read the image of an uninstantiated layout")
+        };
+        let decoded = Decoded::read(&dir.join(path)).map_err(|e| format!("{}: {}", path, e))?;
+
+        Ok(fit(&decoded, self.fit, box_, scale))
+    }
+}
+
+/// Contains an SVG document in `box_`, the way `ImageElement::fit_into` does for files.
+pub fn fit_svg(
+    data: &[u8],
+    box_: &SpatialBox,
+    scale: f32,
+) -> Result<(SpatialBox, RgbaImage), String> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default()).map_err(|e| e.to_string())?;
+
+    Ok(fit(
+        &Decoded::Svg(Box::new(tree)),
+        ImageFit::Contain,
+        box_,
+        scale,
+    ))
+}
+
+fn fit(
+    decoded: &Decoded,
+    image_fit: ImageFit,
+    box_: &SpatialBox,
+    scale: f32,
+) -> (SpatialBox, RgbaImage) {
+    let (image_width, image_height) = decoded.size();
+    let ratio = match image_fit {
+        ImageFit::Contain => (box_.width() / image_width).min(box_.height() / image_height),
+        ImageFit::Cover => (box_.width() / image_width).max(box_.height() / image_height),
+    };
+    let (width, height) = (image_width * ratio, image_height * ratio);
+    let pixels = |points: f32| ((points * scale).round() as u32).max(1);
+    let image = decoded.rasterize(pixels(width), pixels(height));
+
+    match image_fit {
+        ImageFit::Contain => {
+            let top_left = box_
+                .top_left
+                .move_x_by((box_.width() - width) / 2.0)
+                .move_y_by((box_.height() - height) / 2.0);
+            let drawn = SpatialBox::new(
+                top_left,
+                Point::new(top_left.x + width, top_left.y + height),
+            );
+            (drawn, image)
+        }
+        ImageFit::Cover => {
+            let (crop_width, crop_height) = (
+                pixels(box_.width()).min(image.width()),
+                pixels(box_.height()).min(image.height()),
+            );
+            let cropped = imageops::crop_imm(
+                &image,
+                (image.width() - crop_width) / 2,
+                (image.height() - crop_height) / 2,
+                crop_width,
+                crop_height,
+            )
+            .to_image();
+            (*box_, cropped)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_into() {
+        let dir = std::env::temp_dir().join(format!("cvdl-image-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // A wide image, red on the left half and blue on the right one
+        RgbaImage::from_fn(40, 20, |x, _| match x < 20 {
+            true => Rgba([255, 0, 0, 255]),
+            false => Rgba([0, 0, 255, 255]),
+        })
+        .save(dir.join("wide.png"))
+        .unwrap();
+
+        let image: ImageElement = serde_json::from_str(
+            r#"{ "source": { "Ref": "Photo" }, "width": "10pt", "height": 10 }"#,
+        )
+        .unwrap();
+        assert!(!image.is_instantiated());
+        let section = [(
+            "Photo".to_string(),
+            ItemContent::String("wide.png".to_string()),
+        )]
+        .into_iter()
+        .collect();
+        let image = image.instantiate(&section).unwrap();
+        assert_eq!(image.source, ImageSource::Path("wide.png".to_string()));
+
+        let box_ = SpatialBox::new(Point::new(5.0, 5.0), Point::new(15.0, 15.0));
+        let (drawn, pixels) = image.fit_into(&dir, &box_, 2.0).unwrap();
+        assert_eq!(
+            drawn,
+            SpatialBox::new(Point::new(5.0, 7.5), Point::new(15.0, 12.5))
+        );
+        assert_eq!(pixels.dimensions(), (20, 10));
+
+        // Covering crops the sides, so both halves remain
+        let image = ImageElement {
+            fit: ImageFit::Cover,
+            circular: true,
+            ..image
+        };
+        let (drawn, pixels) = image.fit_into(&dir, &box_, 1.0).unwrap();
+        assert_eq!(drawn, box_);
+        assert_eq!(pixels.dimensions(), (10, 10));
+        assert_eq!(pixels.get_pixel(0, 5), &Rgba([255, 0, 0, 255]));
+        assert_eq!(pixels.get_pixel(9, 5), &Rgba([0, 0, 255, 255]));
+        assert_eq!(image.clip_radius(&box_), Some(5.0));
+
+        assert!(image
+            .with_source(ImageSource::Path("missing.png".to_string()))
+            .fit_into(&dir, &box_, 1.0)
+            .is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,18 +2,29 @@ use std::collections::HashMap;
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    alignment::Alignment,
+    alignment::{Alignment, VerticalAlignment},
     any_layout::ElementBox,
-    basic_layout::BasicLayout,
     container::Container,
+    diagnostic::Diagnostic,
+    direction::Direction,
     element::Element,
     font::{Font, FontDict},
+    grid::Grid,
+    hyphenation::Hyphenation,
+    icon::Icon,
+    image_element::ImageElement,
     margin::Margin,
+    meter::Meter,
+    overflow::Overflow,
     point::Point,
     resume_data::ItemContent,
+    shape::Rule,
     spatial_box::SpatialBox,
+    style::{Style, Styles},
+    timeline::Timeline,
     width::Width,
 };
 
@@ -24,19 +35,12 @@ pub enum SectionLayout {
     FlexRow(Container),
     Text(Element),
     Ref(Element),
-}
-
-// todo: use this
-impl From<SectionLayout> for BasicLayout {
-    fn from(value: SectionLayout) -> Self {
-        match value {
-            SectionLayout::Stack(_container) => todo!(),
-            SectionLayout::FrozenRow(_) => unreachable!("FrozenRow should be converted to FlexRow"),
-            SectionLayout::FlexRow(_container) => todo!(),
-            SectionLayout::Text(_element) => todo!(),
-            SectionLayout::Ref(_) => unreachable!("Ref should be converted to Text"),
-        }
-    }
+    Rule(Rule),
+    Image(ImageElement),
+    Icon(Icon),
+    Meter(Meter),
+    Timeline(Timeline),
+    Grid(Grid),
 }
 
 impl Display for SectionLayout {
@@ -47,6 +51,30 @@ impl Display for SectionLayout {
             SectionLayout::FlexRow(container) => write!(f, "{}", container),
             SectionLayout::Text(element) => write!(f, "{}", element),
             SectionLayout::Ref(element) => write!(f, "{}", element),
+            SectionLayout::Rule(_) => write!(f, "---"),
+            SectionLayout::Image(image) => write!(f, "{:?}", image.source),
+            SectionLayout::Icon(icon) => write!(f, "{:?}", icon.source),
+            SectionLayout::Meter(meter) => write!(f, "{:?}", meter.value),
+            SectionLayout::Timeline(timeline) => write!(
+                f,
+                "{} | {}",
+                timeline.date,
+                timeline
+                    .elements
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            SectionLayout::Grid(grid) => write!(
+                f,
+                "{}",
+                grid.cells
+                    .iter()
+                    .map(|c| c.element.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" | ")
+            ),
         }
     }
 }
@@ -76,6 +104,36 @@ impl SectionLayout {
         log::debug!("Creating new ref element: {}", element.uid);
         SectionLayout::Ref(element)
     }
+
+    pub fn new_rule(rule: Rule) -> SectionLayout {
+        log::debug!("Creating new rule: {}", rule.uid);
+        SectionLayout::Rule(rule)
+    }
+
+    pub fn new_image(image: ImageElement) -> SectionLayout {
+        log::debug!("Creating new image: {}", image.uid);
+        SectionLayout::Image(image)
+    }
+
+    pub fn new_icon(icon: Icon) -> SectionLayout {
+        log::debug!("Creating new icon: {}", icon.uid);
+        SectionLayout::Icon(icon)
+    }
+
+    pub fn new_meter(meter: Meter) -> SectionLayout {
+        log::debug!("Creating new meter: {}", meter.uid);
+        SectionLayout::Meter(meter)
+    }
+
+    pub fn new_timeline(timeline: Timeline) -> SectionLayout {
+        log::debug!("Creating new timeline: {}", timeline.uid);
+        SectionLayout::Timeline(timeline)
+    }
+
+    pub fn new_grid(grid: Grid) -> SectionLayout {
+        log::debug!("Creating new grid: {}", grid.uid);
+        SectionLayout::Grid(grid)
+    }
 }
 
 impl SectionLayout {
@@ -87,6 +145,12 @@ impl SectionLayout {
             SectionLayout::FlexRow(_) => "flex_row".to_string(),
             SectionLayout::Text(_) => "text".to_string(),
             SectionLayout::Ref(_) => "ref".to_string(),
+            SectionLayout::Rule(_) => "rule".to_string(),
+            SectionLayout::Image(_) => "image".to_string(),
+            SectionLayout::Icon(_) => "icon".to_string(),
+            SectionLayout::Meter(_) => "meter".to_string(),
+            SectionLayout::Timeline(_) => "timeline".to_string(),
+            SectionLayout::Grid(_) => "grid".to_string(),
         }
     }
 
@@ -96,6 +160,12 @@ impl SectionLayout {
             | SectionLayout::FrozenRow(container)
             | SectionLayout::FlexRow(container) => container.width,
             SectionLayout::Text(element) | SectionLayout::Ref(element) => element.width,
+            SectionLayout::Rule(rule) => rule.width,
+            SectionLayout::Image(image) => image.width,
+            SectionLayout::Icon(icon) => Width::Absolute(icon.size()),
+            SectionLayout::Meter(meter) => meter.width(),
+            SectionLayout::Timeline(timeline) => timeline.width,
+            SectionLayout::Grid(grid) => grid.width,
         }
     }
     #[allow(dead_code)]
@@ -103,8 +173,14 @@ impl SectionLayout {
         match self {
             SectionLayout::Stack(container)
             | SectionLayout::FrozenRow(container)
-            | SectionLayout::FlexRow(container) => container.margin,
-            SectionLayout::Text(element) | SectionLayout::Ref(element) => element.margin,
+            | SectionLayout::FlexRow(container) => container.margin(),
+            SectionLayout::Text(element) | SectionLayout::Ref(element) => element.margin(),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_)
+            | SectionLayout::Timeline(_)
+            | SectionLayout::Grid(_) => Margin::default(),
         }
     }
     #[allow(dead_code)]
@@ -112,8 +188,14 @@ impl SectionLayout {
         match self {
             SectionLayout::Stack(container)
             | SectionLayout::FrozenRow(container)
-            | SectionLayout::FlexRow(container) => container.alignment,
-            SectionLayout::Text(element) | SectionLayout::Ref(element) => element.alignment,
+            | SectionLayout::FlexRow(container) => container.alignment(),
+            SectionLayout::Text(element) | SectionLayout::Ref(element) => element.alignment(),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_)
+            | SectionLayout::Timeline(_)
+            | SectionLayout::Grid(_) => Alignment::default(),
         }
     }
 
@@ -123,8 +205,12 @@ impl SectionLayout {
             | SectionLayout::FrozenRow(container)
             | SectionLayout::FlexRow(container) => container.fonts(),
             SectionLayout::Text(element) | SectionLayout::Ref(element) => {
-                vec![element.font.clone()]
+                vec![element.font()]
             }
+            SectionLayout::Rule(_) | SectionLayout::Image(_) | SectionLayout::Meter(_) => vec![],
+            SectionLayout::Icon(icon) => vec![icon.font()],
+            SectionLayout::Timeline(timeline) => timeline.fonts(),
+            SectionLayout::Grid(grid) => grid.fonts(),
         }
     }
     #[allow(dead_code)]
@@ -141,6 +227,12 @@ impl SectionLayout {
             }
             SectionLayout::Text(element) => SectionLayout::new_text(element.with_margin(margin)),
             SectionLayout::Ref(element) => SectionLayout::new_ref(element.with_margin(margin)),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_)
+            | SectionLayout::Timeline(_)
+            | SectionLayout::Grid(_) => self.clone(),
         }
     }
     #[allow(dead_code)]
@@ -161,6 +253,120 @@ impl SectionLayout {
             SectionLayout::Ref(element) => {
                 SectionLayout::new_ref(element.with_alignment(alignment))
             }
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_)
+            | SectionLayout::Timeline(_)
+            | SectionLayout::Grid(_) => self.clone(),
+        }
+    }
+
+    pub fn with_direction(&self, direction: Direction) -> SectionLayout {
+        match self {
+            SectionLayout::Stack(c) => SectionLayout::new_stack(c.with_direction(direction)),
+            SectionLayout::FrozenRow(c) => {
+                SectionLayout::new_frozen_row(c.with_direction(direction))
+            }
+            SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(c.with_direction(direction)),
+            SectionLayout::Text(e) => SectionLayout::new_text(e.with_direction(direction)),
+            SectionLayout::Ref(e) => SectionLayout::new_ref(e.with_direction(direction)),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.with_direction(direction)))
+            }
+            SectionLayout::Grid(g) => {
+                SectionLayout::new_grid(g.map(|e| e.with_direction(direction)))
+            }
+        }
+    }
+
+    pub fn inherit_hyphenation(&self, hyphenation: &Option<Hyphenation>) -> SectionLayout {
+        match self {
+            SectionLayout::Stack(c) => SectionLayout::new_stack(c.inherit_hyphenation(hyphenation)),
+            SectionLayout::FrozenRow(c) => {
+                SectionLayout::new_frozen_row(c.inherit_hyphenation(hyphenation))
+            }
+            SectionLayout::FlexRow(c) => {
+                SectionLayout::new_flex_row(c.inherit_hyphenation(hyphenation))
+            }
+            SectionLayout::Text(e) => SectionLayout::new_text(e.inherit_hyphenation(hyphenation)),
+            SectionLayout::Ref(e) => SectionLayout::new_ref(e.inherit_hyphenation(hyphenation)),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.inherit_hyphenation(hyphenation)))
+            }
+            SectionLayout::Grid(g) => {
+                SectionLayout::new_grid(g.map(|e| e.inherit_hyphenation(hyphenation)))
+            }
+        }
+    }
+
+    /// Resolves the `style` references of the layout against `styles`, `inherited` is the
+    /// font and the color cascading from the containers above it.
+    pub fn resolve_styles(&self, styles: &Styles, inherited: &Style) -> SectionLayout {
+        match self {
+            SectionLayout::Stack(c) => {
+                SectionLayout::new_stack(c.resolve_styles(styles, inherited))
+            }
+            SectionLayout::FrozenRow(c) => {
+                SectionLayout::new_frozen_row(c.resolve_styles(styles, inherited))
+            }
+            SectionLayout::FlexRow(c) => {
+                SectionLayout::new_flex_row(c.resolve_styles(styles, inherited))
+            }
+            SectionLayout::Text(e) => SectionLayout::new_text(
+                e.with_style(&Style::find(&e.style, styles).over(inherited)),
+            ),
+            SectionLayout::Ref(e) => {
+                SectionLayout::new_ref(e.with_style(&Style::find(&e.style, styles).over(inherited)))
+            }
+            SectionLayout::Icon(i) => SectionLayout::new_icon(
+                i.with_style(&Style::find(&i.style, styles).over(inherited)),
+            ),
+            // Rules and meters have no style of their own, the name is taken by their line
+            // and mark styles, so they only take the color cascading from their containers
+            SectionLayout::Rule(r) => SectionLayout::new_rule(r.with_style(inherited)),
+            SectionLayout::Meter(m) => SectionLayout::new_meter(m.with_style(inherited)),
+            SectionLayout::Image(_) => self.clone(),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.resolve_styles(styles, inherited)))
+            }
+            SectionLayout::Grid(g) => {
+                SectionLayout::new_grid(g.map(|e| e.resolve_styles(styles, inherited)))
+            }
+        }
+    }
+
+    pub fn inherit_font_fallbacks(&self, fallbacks: &[String]) -> SectionLayout {
+        match self {
+            SectionLayout::Stack(c) => {
+                SectionLayout::new_stack(c.inherit_font_fallbacks(fallbacks))
+            }
+            SectionLayout::FrozenRow(c) => {
+                SectionLayout::new_frozen_row(c.inherit_font_fallbacks(fallbacks))
+            }
+            SectionLayout::FlexRow(c) => {
+                SectionLayout::new_flex_row(c.inherit_font_fallbacks(fallbacks))
+            }
+            SectionLayout::Text(e) => SectionLayout::new_text(e.inherit_font_fallbacks(fallbacks)),
+            SectionLayout::Ref(e) => SectionLayout::new_ref(e.inherit_font_fallbacks(fallbacks)),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.inherit_font_fallbacks(fallbacks)))
+            }
+            SectionLayout::Grid(g) => {
+                SectionLayout::new_grid(g.map(|e| e.inherit_font_fallbacks(fallbacks)))
+            }
         }
     }
 
@@ -170,8 +376,13 @@ impl SectionLayout {
             SectionLayout::Stack(c) | SectionLayout::FrozenRow(c) | SectionLayout::FlexRow(c) => {
                 c.elements.iter().all(|e| e.is_instantiated())
             }
-            SectionLayout::Text(_) => true,
+            SectionLayout::Text(_) | SectionLayout::Rule(_) => true,
             SectionLayout::Ref(_) => false,
+            SectionLayout::Image(i) => i.is_instantiated(),
+            SectionLayout::Icon(i) => i.is_instantiated(),
+            SectionLayout::Meter(m) => m.is_instantiated(),
+            SectionLayout::Timeline(t) => t.is_instantiated(),
+            SectionLayout::Grid(g) => g.is_instantiated(),
         }
     }
 
@@ -182,6 +393,23 @@ impl SectionLayout {
             SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(c.instantiate(section)),
             SectionLayout::Text(e) => SectionLayout::new_text(e.clone()),
             SectionLayout::Ref(e) => SectionLayout::instantiate_ref_element(e.clone(), section),
+            SectionLayout::Rule(r) => SectionLayout::new_rule(r.clone()),
+            SectionLayout::Image(i) => match i.instantiate(section) {
+                Some(image) => SectionLayout::new_image(image),
+                None => SectionLayout::Stack(Container::empty_container()),
+            },
+            SectionLayout::Icon(i) => match i.instantiate(section) {
+                Some(icon) => SectionLayout::new_icon(icon),
+                None => SectionLayout::Stack(Container::empty_container()),
+            },
+            SectionLayout::Meter(m) => match m.instantiate(section) {
+                Some(meter) => SectionLayout::new_meter(meter),
+                None => SectionLayout::Stack(Container::empty_container()),
+            },
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.instantiate(section)))
+            }
+            SectionLayout::Grid(g) => SectionLayout::new_grid(g.map(|e| e.instantiate(section))),
         }
     }
 
@@ -190,7 +418,9 @@ impl SectionLayout {
         section: &HashMap<String, ItemContent>,
     ) -> SectionLayout {
         if let Some(text) = section.get(&element.item) {
-            let mut element = element.with_item(text.to_string());
+            let mut element = element
+                .with_item(text.to_string())
+                .with_field(element.item.clone());
 
             if let ItemContent::Url { url, text: _ } = text {
                 element = element.with_url(url.clone())
@@ -203,19 +433,19 @@ impl SectionLayout {
     }
 
     pub fn bound_width(&self, width: f32) -> SectionLayout {
-        let bound = match self.width() {
-            Width::Absolute(w) => f32::min(w, width),
-            Width::Percentage(_) => unreachable!(
-                "SectionLayout::bound_width: Cannot bounded width for non-unitized widths!"
-            ),
-            Width::Fill => width,
-        };
+        let bound = self.width().bound(width).get_fixed_unchecked();
 
         match self {
             SectionLayout::Stack(c) => SectionLayout::new_stack(c.bound_width(bound)),
             SectionLayout::FrozenRow(c) => SectionLayout::new_frozen_row(c.bound_width(bound)),
             SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(c.bound_width(bound)),
             SectionLayout::Text(e) => SectionLayout::new_text(e.bound_width(bound)),
+            SectionLayout::Rule(r) => SectionLayout::new_rule(r.bound_width(bound)),
+            SectionLayout::Image(i) => SectionLayout::new_image(i.bound_width(bound)),
+            SectionLayout::Meter(m) => SectionLayout::new_meter(m.bound_width(bound)),
+            SectionLayout::Timeline(t) => SectionLayout::new_timeline(t.bound_width(bound)),
+            SectionLayout::Grid(g) => SectionLayout::new_grid(g.bound_width(bound)),
+            SectionLayout::Icon(_) => self.clone(),
             SectionLayout::Ref(_) => {
                 unreachable!("Cannot propagate widths of uninstantiated layout")
             }
@@ -230,11 +460,32 @@ impl SectionLayout {
             }
             SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(c.scale_width(document_width)),
             SectionLayout::Text(e) => SectionLayout::new_text(e.scale_width(document_width)),
+            SectionLayout::Rule(r) => SectionLayout::new_rule(r.scale_width(document_width)),
+            SectionLayout::Image(i) => SectionLayout::new_image(i.scale_width(document_width)),
+            SectionLayout::Meter(m) => SectionLayout::new_meter(m.scale_width(document_width)),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.scale_width(document_width))
+            }
+            SectionLayout::Grid(g) => SectionLayout::new_grid(g.scale_width(document_width)),
+            SectionLayout::Icon(_) => self.clone(),
             SectionLayout::Ref(_) => unreachable!("Cannot scale width of uninstantiated layout"),
         }
     }
 
     pub fn normalize(&self, width: f32, font_dict: &FontDict) -> SectionLayout {
+        let (layout, diagnostics) = self.normalize_with_diagnostics(width, font_dict);
+        for diagnostic in diagnostics {
+            log::warn!("{}", diagnostic);
+        }
+        layout
+    }
+
+    /// Normalizes the layout, returning the problems it recovered from along with it.
+    pub fn normalize_with_diagnostics(
+        &self,
+        width: f32,
+        font_dict: &FontDict,
+    ) -> (SectionLayout, Vec<Diagnostic>) {
         log::debug!(
             "Normalizing document, checking if {} is instantiated...",
             self
@@ -257,13 +508,18 @@ impl SectionLayout {
 
         let font_filled_layout = bounded_layout.fill_fonts(font_dict);
 
-        log::debug!("Fonts filled. Breaking lines...");
+        log::debug!("Fonts filled. Fitting overflows...");
 
-        let broken_layout = font_filled_layout.break_lines(font_dict);
+        let mut diagnostics = Vec::new();
+        let fitted_layout = font_filled_layout.fit_overflows(font_dict, &mut diagnostics);
+
+        log::debug!("Overflows fitted. Breaking lines...");
+
+        let broken_layout = fitted_layout.break_lines(font_dict, &mut diagnostics);
 
         log::debug!("Lines broken.");
 
-        broken_layout
+        (broken_layout, diagnostics)
     }
 
     pub fn fill_fonts(&self, font_dict: &FontDict) -> SectionLayout {
@@ -272,68 +528,373 @@ impl SectionLayout {
             SectionLayout::FrozenRow(c) => SectionLayout::new_frozen_row(c.fill_fonts(font_dict)),
             SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(c.fill_fonts(font_dict)),
             SectionLayout::Text(e) => SectionLayout::new_text(e.fill_fonts(font_dict)),
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.fill_fonts(font_dict)))
+            }
+            SectionLayout::Grid(g) => SectionLayout::new_grid(g.fill_fonts(font_dict)),
             SectionLayout::Ref(_) => unreachable!("Cannot fill fonts of uninstantiated layout"),
         }
     }
 
-    pub fn break_lines(&self, font_dict: &FontDict) -> SectionLayout {
+    /// Fits text and frozen rows too wide for their boxes following their overflow policies,
+    /// reporting each of them.
+    pub fn fit_overflows(
+        &self,
+        font_dict: &FontDict,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> SectionLayout {
+        match self {
+            SectionLayout::Stack(c) => SectionLayout::new_stack(
+                c.with_elements(
+                    c.elements
+                        .iter()
+                        .map(|e| e.fit_overflows(font_dict, diagnostics))
+                        .collect(),
+                ),
+            ),
+            SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(
+                c.with_elements(
+                    c.elements
+                        .iter()
+                        .map(|e| e.fit_overflows(font_dict, diagnostics))
+                        .collect(),
+                ),
+            ),
+            SectionLayout::FrozenRow(c) => {
+                let c = c.with_elements(
+                    c.elements
+                        .iter()
+                        .map(|e| e.fit_overflows(font_dict, diagnostics))
+                        .collect(),
+                );
+                SectionLayout::fit_frozen_row(&c, font_dict, diagnostics)
+            }
+            SectionLayout::Text(e) => {
+                let (width, text_width) = (
+                    e.width.get_fixed_unchecked(),
+                    e.text_width.get_fixed_unchecked(),
+                );
+                if e.overflow == Overflow::Wrap || text_width <= width {
+                    return self.clone();
+                }
+                let fitted = e.fit_overflow(width, e.overflow, font_dict);
+                diagnostics.push(Diagnostic::new(
+                    e.field_name(),
+                    overflow_message(text_width, width, &fitted, e.overflow),
+                ));
+                SectionLayout::new_text(fitted)
+            }
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.fit_overflows(font_dict, diagnostics)))
+            }
+            SectionLayout::Grid(g) => {
+                SectionLayout::new_grid(g.map(|e| e.fit_overflows(font_dict, diagnostics)))
+            }
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+            SectionLayout::Ref(_) => unreachable!("Cannot fit uninstantiated layout"),
+        }
+    }
+
+    // Shrinks or cuts the text of a frozen row too wide for its width, rows that cannot be
+    // fitted wrap
+    fn fit_frozen_row(
+        c: &Container,
+        font_dict: &FontDict,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> SectionLayout {
+        let (total_width, width) = (c.elements_width(), c.inner_width());
+        if !c.overflows() {
+            return SectionLayout::new_frozen_row(c.clone());
+        }
+
+        let texts = c
+            .elements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, e)| match e {
+                SectionLayout::Text(e) => Some((index, e)),
+                _ => None,
+            })
+            .collect::<Vec<(usize, &Element)>>();
+        let fields = texts
+            .iter()
+            .map(|(_, e)| e.field_name())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let excess = total_width - width;
+        let text_widths: f32 = texts
+            .iter()
+            .map(|(_, e)| e.width.get_fixed_unchecked())
+            .sum();
+
+        let mut elements = c.elements.clone();
+        match c.overflow {
+            Overflow::Shrink if text_widths > excess => {
+                // All of the text is shrunk in proportion, so the row keeps its look
+                let ratio = (text_widths - excess) / text_widths;
+                for (index, e) in texts.iter() {
+                    let width = e.width.get_fixed_unchecked() * ratio;
+                    elements[*index] =
+                        SectionLayout::new_text(e.fit_overflow(width, Overflow::Shrink, font_dict));
+                }
+            }
+            Overflow::Ellipsis | Overflow::Clip => {
+                // Only the widest text is cut, it is the one that has the most to spare
+                let widest = texts.iter().max_by(|(_, a), (_, b)| {
+                    a.width
+                        .get_fixed_unchecked()
+                        .total_cmp(&b.width.get_fixed_unchecked())
+                });
+                if let Some((index, e)) =
+                    widest.filter(|(_, e)| e.width.get_fixed_unchecked() > excess)
+                {
+                    let width = e.width.get_fixed_unchecked() - excess;
+                    elements[*index] =
+                        SectionLayout::new_text(e.fit_overflow(width, c.overflow, font_dict));
+                }
+            }
+            _ => {}
+        }
+
+        let fitted = c.with_elements(elements);
+        if !fitted.overflows() && c.overflow != Overflow::Wrap {
+            diagnostics.push(Diagnostic::new(
+                fields,
+                format!(
+                    "row is {:.1}pt wide for {:.1}pt, {}",
+                    total_width,
+                    width,
+                    match c.overflow {
+                        Overflow::Shrink => "its text is shrunk",
+                        Overflow::Ellipsis => "its text is cut with an ellipsis",
+                        _ => "its text is clipped",
+                    }
+                ),
+            ));
+            SectionLayout::new_frozen_row(fitted)
+        } else {
+            diagnostics.push(Diagnostic::new(
+                fields,
+                format!(
+                    "row is {:.1}pt wide for {:.1}pt, its elements are wrapped",
+                    total_width, width
+                ),
+            ));
+            SectionLayout::new_flex_row(c.clone())
+        }
+    }
+
+    pub fn break_lines(
+        &self,
+        font_dict: &FontDict,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> SectionLayout {
         match self {
             SectionLayout::Stack(c) => {
                 let new_stack = SectionLayout::new_stack(
                     c.with_elements(
                         c.elements
                             .iter()
-                            .map(|e| e.break_lines(font_dict))
+                            .map(|e| e.break_lines(font_dict, diagnostics))
                             .collect(),
                     ),
                 );
                 new_stack
             }
             SectionLayout::FrozenRow(c) => {
-                let total_width = c
-                    .elements
-                    .iter()
-                    .map(|e| e.width().get_fixed_unchecked())
-                    .sum::<f32>();
-                if total_width > self.width().get_fixed_unchecked() {
-                    panic!(
-                        "Cannot break lines of frozen row with width {:?} and total width {}",
-                        self.width(),
-                        total_width
-                    );
+                if c.overflows() {
+                    // Overflowing rows are fitted before their lines are broken, the ones that
+                    // still do not fit wrap like flex rows
+                    SectionLayout::new_flex_row(c.clone()).break_lines(font_dict, diagnostics)
                 } else {
                     SectionLayout::new_flex_row(Container {
                         uid: c.uid,
                         elements: c
                             .elements
                             .iter()
-                            .map(|e| e.break_lines(font_dict))
+                            .map(|e| e.break_lines(font_dict, diagnostics))
                             .collect(),
                         margin: c.margin,
                         alignment: c.alignment,
                         width: c.width,
+                        height: c.height,
+                        vertical_alignment: c.vertical_alignment,
+                        gap: c.gap,
+                        background: c.background,
+                        border: c.border,
+                        overflow: c.overflow,
+                        style: c.style.clone(),
                     })
                 }
             }
             SectionLayout::FlexRow(c) => {
-                let lines: Vec<Container> = c.break_lines(font_dict);
+                let lines: Vec<Container> = c.break_lines(font_dict, diagnostics);
                 SectionLayout::new_stack(
                     c.with_elements(lines.into_iter().map(SectionLayout::FlexRow).collect()),
                 )
             }
             SectionLayout::Text(e) => {
-                let lines: Vec<Element> = e.break_lines(font_dict);
+                let lines: Vec<Element> = e.break_lines(font_dict, diagnostics);
                 SectionLayout::new_stack(
                     Container::empty_container()
                         .with_elements(lines.into_iter().map(SectionLayout::new_text).collect())
-                        .with_alignment(e.alignment)
-                        .with_margin(e.margin)
+                        .with_alignment(e.alignment())
+                        .with_margin(e.margin())
                         .with_width(e.width),
                 )
             }
+            SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.break_lines(font_dict, diagnostics)))
+            }
+            SectionLayout::Grid(g) => {
+                SectionLayout::new_grid(g.map(|e| e.break_lines(font_dict, diagnostics)))
+            }
             SectionLayout::Ref(_) => unreachable!("Cannot break lines of uninstantiated layout"),
         }
     }
+
+    /// The width the layout takes when it is not stretched, the width of its text on a
+    /// single line for filling layouts, within their bounds.
+    pub fn content_width(&self, font_dict: &FontDict) -> f32 {
+        let widest = |elements: &[SectionLayout]| {
+            elements
+                .iter()
+                .map(|e| e.content_width(font_dict))
+                .fold(0.0, f32::max)
+        };
+        let content = match self {
+            SectionLayout::Stack(c) => widest(&c.elements) + 2.0 * c.inset(),
+            SectionLayout::FrozenRow(c) | SectionLayout::FlexRow(c) => {
+                let gaps = c.elements.len().saturating_sub(1) as f32 * c.gap;
+                c.elements
+                    .iter()
+                    .map(|e| e.content_width(font_dict))
+                    .sum::<f32>()
+                    + gaps
+                    + 2.0 * c.inset()
+            }
+            SectionLayout::Text(e) => e.measure(
+                &e.text_transform.apply(&e.item, &e.font(), font_dict),
+                font_dict,
+            ),
+            SectionLayout::Rule(_) | SectionLayout::Image(_) | SectionLayout::Meter(_) => 0.0,
+            SectionLayout::Icon(i) => i.size(),
+            SectionLayout::Timeline(t) => t.body_offset() + widest(&t.elements),
+            SectionLayout::Grid(g) => g.content_width(font_dict),
+            SectionLayout::Ref(_) => unreachable!("Cannot measure uninstantiated layout"),
+        };
+
+        match self.width() {
+            width if width.is_fixed() => width.get_fixed_unchecked(),
+            width => width.clamp(content),
+        }
+    }
+
+    /// Collects the widths of the auto columns of the grids aligned across the section,
+    /// the widest cell of each column of every grid of every item.
+    pub fn measure_grids(&self, font_dict: &FontDict, widths: &mut HashMap<Uuid, Vec<f32>>) {
+        match self {
+            SectionLayout::Stack(c) | SectionLayout::FrozenRow(c) | SectionLayout::FlexRow(c) => c
+                .elements
+                .iter()
+                .for_each(|e| e.measure_grids(font_dict, widths)),
+            SectionLayout::Timeline(t) => {
+                t.date.measure_grids(font_dict, widths);
+                t.elements
+                    .iter()
+                    .for_each(|e| e.measure_grids(font_dict, widths));
+            }
+            SectionLayout::Grid(g) => {
+                if g.align_section {
+                    let measured = g.auto_widths(font_dict);
+                    let section = widths.entry(g.uid).or_insert(vec![0.0; measured.len()]);
+                    for (width, measured) in section.iter_mut().zip(measured) {
+                        *width = f32::max(*width, measured);
+                    }
+                }
+                g.cells
+                    .iter()
+                    .for_each(|c| c.element.measure_grids(font_dict, widths));
+            }
+            SectionLayout::Text(_)
+            | SectionLayout::Ref(_)
+            | SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => {}
+        }
+    }
+
+    pub fn with_section_widths(&self, widths: &HashMap<Uuid, Vec<f32>>) -> SectionLayout {
+        match self {
+            SectionLayout::Stack(c) => SectionLayout::new_stack(
+                c.with_elements(
+                    c.elements
+                        .iter()
+                        .map(|e| e.with_section_widths(widths))
+                        .collect(),
+                ),
+            ),
+            SectionLayout::FrozenRow(c) => SectionLayout::new_frozen_row(
+                c.with_elements(
+                    c.elements
+                        .iter()
+                        .map(|e| e.with_section_widths(widths))
+                        .collect(),
+                ),
+            ),
+            SectionLayout::FlexRow(c) => SectionLayout::new_flex_row(
+                c.with_elements(
+                    c.elements
+                        .iter()
+                        .map(|e| e.with_section_widths(widths))
+                        .collect(),
+                ),
+            ),
+            SectionLayout::Timeline(t) => {
+                SectionLayout::new_timeline(t.map(|e| e.with_section_widths(widths)))
+            }
+            SectionLayout::Grid(g) => {
+                let g = g.map(|e| e.with_section_widths(widths));
+                match widths.get(&g.uid) {
+                    Some(section_widths) => {
+                        SectionLayout::new_grid(g.with_section_widths(section_widths.clone()))
+                    }
+                    None => SectionLayout::new_grid(g),
+                }
+            }
+            SectionLayout::Text(_)
+            | SectionLayout::Ref(_)
+            | SectionLayout::Rule(_)
+            | SectionLayout::Image(_)
+            | SectionLayout::Icon(_)
+            | SectionLayout::Meter(_) => self.clone(),
+        }
+    }
+}
+
+fn overflow_message(text_width: f32, width: f32, fitted: &Element, overflow: Overflow) -> String {
+    let fix = match overflow {
+        Overflow::Shrink => format!("its font is shrunk to {:.1}pt", fitted.font().size),
+        Overflow::Ellipsis => format!("it is cut to \"{}\"", fitted.item),
+        Overflow::Clip => format!("it is clipped to \"{}\"", fitted.item),
+        Overflow::Wrap => "it is wrapped".to_string(),
+    };
+    format!(
+        "text is {:.1}pt wide for {:.1}pt, {}",
+        text_width, width, fix
+    )
 }
 
 impl SectionLayout {
@@ -346,7 +907,9 @@ impl SectionLayout {
             Point::new(0.0, 0.0),
             Point::new(self.width().get_fixed_unchecked(), depth),
         );
-        ElementBox::new(bounding_box, textbox_positions)
+        let mut element_box = ElementBox::new(bounding_box, textbox_positions);
+        element_box.breakable = matches!(self, SectionLayout::Timeline(_));
+        element_box
     }
 
     fn compute_textbox_positions(
@@ -355,62 +918,698 @@ impl SectionLayout {
         top_left: Point,
         font_dict: &FontDict,
     ) -> f32 {
-        match self {
+        // The background and the border of a container are an empty element pushed before
+        // its elements, so that they are drawn behind them. Its box is known once they are
+        // laid out.
+        let decoration = match self {
+            SectionLayout::Stack(c) | SectionLayout::FlexRow(c) => c.shape().map(|shape| {
+                textbox_positions.push((
+                    SpatialBox::new(top_left, top_left),
+                    Element::shape_box(shape),
+                ));
+                textbox_positions.len() - 1
+            }),
+            _ => None,
+        };
+
+        let depth = match self {
             SectionLayout::Stack(c) => {
-                let mut top_left = top_left;
+                let start_y = top_left.y;
+                let first = textbox_positions.len();
+                let mut top_left = top_left.move_x_by(c.inset()).move_y_by(c.inset());
                 let mut depth = top_left.y;
-                for element in c.elements.iter() {
+                for (index, element) in c.elements.iter().enumerate() {
+                    if index > 0 {
+                        top_left = top_left.move_y_by(c.gap);
+                    }
                     depth =
                         element.compute_textbox_positions(textbox_positions, top_left, font_dict);
                     top_left = top_left.move_y_to(depth);
                 }
-                depth
+                let content_height = depth + c.inset() - start_y;
+                let height = c.height.resolve(content_height);
+
+                // Elements of a stack higher than its contents, such as the lines of a row
+                // that wraps, follow its vertical alignment
+                let offset = match c.vertical_alignment {
+                    VerticalAlignment::Top | VerticalAlignment::Baseline => 0.0,
+                    VerticalAlignment::Center => (height - content_height) / 2.0,
+                    VerticalAlignment::Bottom => height - content_height,
+                };
+                if offset > 0.0 {
+                    for (textbox, _) in textbox_positions[first..].iter_mut() {
+                        *textbox = textbox.move_y_by(offset);
+                    }
+                }
+
+                start_y + height
             }
             SectionLayout::FlexRow(c) => {
-                let (top_left, per_elem_space) = match c.alignment {
+                // The elements are laid out inside the border of the row
+                let top_left = top_left.move_x_by(c.inset()).move_y_by(c.inset());
+                let (top_left, per_elem_space) = match c.alignment() {
                     Alignment::Left => (top_left, 0.0),
                     Alignment::Center => (
-                        top_left
-                            .move_x_by((c.width.get_fixed_unchecked() - c.elements_width()) / 2.0),
+                        top_left.move_x_by((c.inner_width() - c.elements_width()) / 2.0),
                         0.0,
                     ),
                     Alignment::Right => (
-                        top_left.move_x_by(c.width.get_fixed_unchecked() - c.elements_width()),
+                        top_left.move_x_by(c.inner_width() - c.elements_width()),
                         0.0,
                     ),
+                    // A single element has nothing to be spread against
+                    Alignment::Justified if c.elements.len() < 2 => (top_left, 0.0),
                     Alignment::Justified => (
                         top_left,
-                        (c.width.get_fixed_unchecked() - c.elements_width())
-                            / (c.elements.len() - 1) as f32,
+                        (c.inner_width() - c.elements_width()) / (c.elements.len() - 1) as f32,
                     ),
                 };
 
+                let start_y = top_left.y;
                 let mut top_left = top_left;
-                let mut depth = top_left.y;
 
+                // Each child is laid out from the top of the row first, and then moved
+                // vertically once the height and the baseline of the whole row are known.
+                // Height, baseline and boxes of a child
+                type Child = (f32, f32, Vec<(SpatialBox, Element)>);
+                let mut children: Vec<Child> = Vec::new();
                 for element in c.elements.iter() {
-                    depth =
-                        element.compute_textbox_positions(textbox_positions, top_left, font_dict);
-                    top_left =
-                        top_left.move_x_by(element.width().get_fixed_unchecked() + per_elem_space);
+                    let mut boxes = Vec::new();
+                    let depth = element.compute_textbox_positions(&mut boxes, top_left, font_dict);
+                    children.push((depth - start_y, element.baseline(font_dict), boxes));
+                    top_left = top_left
+                        .move_x_by(element.width().get_fixed_unchecked() + c.gap + per_elem_space);
                 }
-                depth
+
+                let row_baseline = children
+                    .iter()
+                    .map(|(_, baseline, _)| *baseline)
+                    .fold(0.0, f32::max);
+
+                let content_height = children
+                    .iter()
+                    .map(|(height, baseline, _)| match c.vertical_alignment {
+                        VerticalAlignment::Baseline => row_baseline - baseline + height,
+                        _ => *height,
+                    })
+                    .fold(0.0, f32::max);
+
+                let insets = 2.0 * c.inset();
+                let row_height = c.height.resolve(content_height + insets) - insets;
+
+                for (height, baseline, boxes) in children {
+                    let offset = match c.vertical_alignment {
+                        VerticalAlignment::Top => 0.0,
+                        VerticalAlignment::Center => (row_height - height) / 2.0,
+                        VerticalAlignment::Bottom => row_height - height,
+                        VerticalAlignment::Baseline => row_baseline - baseline,
+                    };
+                    textbox_positions.extend(
+                        boxes
+                            .into_iter()
+                            .map(|(textbox, element)| (textbox.move_y_by(offset), element)),
+                    );
+                }
+
+                start_y + row_height + c.inset()
             }
             SectionLayout::FrozenRow(_) => {
                 unreachable!("Cannot compute textbox positions of frozen row: {:?}", self)
             }
             SectionLayout::Text(e) => {
-                let width = e.text_width.get_fixed_unchecked();
-                let height = e.font.get_height(font_dict);
+                let text_width = e.text_width.get_fixed_unchecked();
+                let free_space = f32::max(e.width.get_fixed_unchecked() - text_width, 0.0);
+                // Justified lines span the whole element, their words are spread by the backends
+                let (top_left, width) = match e.alignment() {
+                    Alignment::Left => (top_left, text_width),
+                    Alignment::Center => (top_left.move_x_by(free_space / 2.0), text_width),
+                    Alignment::Right => (top_left.move_x_by(free_space), text_width),
+                    Alignment::Justified => (top_left, text_width + free_space),
+                };
+                let height = e.font().get_line_height(font_dict);
                 let textbox =
                     SpatialBox::new(top_left, top_left.move_x_by(width).move_y_by(height));
                 textbox_positions.push((textbox, e.clone()));
 
                 top_left.y + height
             }
+            SectionLayout::Rule(r) => {
+                let width = r.width.get_fixed_unchecked();
+                let line =
+                    SpatialBox::new(top_left, top_left.move_x_by(width).move_y_by(r.height()));
+                textbox_positions.push((line, Element::shape_box(r.shape())));
+
+                top_left.y + r.height()
+            }
+            SectionLayout::Image(i) => {
+                let width = i.width.get_fixed_unchecked();
+                let frame =
+                    SpatialBox::new(top_left, top_left.move_x_by(width).move_y_by(i.height));
+                textbox_positions.push((frame, Element::image_box(i.clone())));
+
+                top_left.y + i.height
+            }
+            SectionLayout::Icon(i) => {
+                // The icon is a square standing on the baseline, in a box as high as a line
+                let bottom = top_left.y + i.font().get_baseline(font_dict);
+                let square = SpatialBox::new(
+                    Point::new(top_left.x, bottom - i.size()),
+                    Point::new(top_left.x + i.size(), bottom),
+                );
+                textbox_positions.push((square, Element::shape_box(i.shape())));
+
+                top_left.y + i.font().get_line_height(font_dict)
+            }
+            SectionLayout::Meter(m) => {
+                for (mark, shape) in m.marks(top_left) {
+                    textbox_positions.push((mark, Element::shape_box(shape)));
+                }
+
+                top_left.y + m.size
+            }
+            SectionLayout::Timeline(t) => {
+                // The spine is pushed first so that it is drawn behind the marker, its box
+                // is known once the date and the elements are laid out
+                let spine = textbox_positions.len();
+                textbox_positions.push((
+                    SpatialBox::new(top_left, top_left),
+                    Element::shape_box(t.spine()),
+                ));
+
+                // The first lines of the date and of the elements share a baseline
+                let baseline = self.baseline(font_dict);
+                let body_baseline = t.elements.first().map_or(0.0, |e| e.baseline(font_dict));
+                let date_top = top_left.move_y_by(baseline - t.date.baseline(font_dict));
+                let date_depth =
+                    t.date
+                        .compute_textbox_positions(textbox_positions, date_top, font_dict);
+
+                let mut body_top = top_left
+                    .move_x_by(t.body_offset())
+                    .move_y_by(baseline - body_baseline);
+                for element in t.elements.iter() {
+                    let depth =
+                        element.compute_textbox_positions(textbox_positions, body_top, font_dict);
+                    body_top = body_top.move_y_to(depth);
+                }
+                let depth = date_depth.max(body_top.y);
+
+                // The marker stands on the baseline, next to the first lines
+                let center = top_left.x + t.spine_x();
+                let marker = SpatialBox::new(
+                    Point::new(center - t.marker / 2.0, top_left.y + baseline - t.marker),
+                    Point::new(center + t.marker / 2.0, top_left.y + baseline),
+                );
+                textbox_positions.push((marker, Element::shape_box(t.marker())));
+
+                // The spine spans the whole entry, so the spines of consecutive entries join
+                textbox_positions[spine].0 = SpatialBox::new(
+                    Point::new(center - t.thickness / 2.0, top_left.y),
+                    Point::new(center + t.thickness / 2.0, depth),
+                );
+
+                depth
+            }
+            SectionLayout::Grid(g) => {
+                let widths = g.column_widths();
+                // Each cell is laid out from the top of the grid first, and moved down to its
+                // row once the heights of the rows are known
+                type Cell = (usize, usize, f32, Vec<(SpatialBox, Element)>);
+                let mut cells: Vec<Cell> = Vec::new();
+                for (cell, (row, column)) in g.cells.iter().zip(g.placements()) {
+                    let (left, _) = g.span(&widths, column, 1);
+                    let mut boxes = Vec::new();
+                    let depth = cell.element.compute_textbox_positions(
+                        &mut boxes,
+                        top_left.move_x_by(left),
+                        font_dict,
+                    );
+                    cells.push((row, cell.row_span.max(1), depth - top_left.y, boxes));
+                }
+
+                // Rows are as high as their highest cell, cells spanning several rows make
+                // the last of them higher when they do not fit
+                let mut heights = vec![0.0; g.row_count()];
+                let mut spanning = cells.iter().collect::<Vec<_>>();
+                spanning.sort_by_key(|(_, span, _, _)| *span);
+                for (row, span, height, _) in spanning {
+                    let spanned = heights[*row..row + span].iter().sum::<f32>()
+                        + (span - 1) as f32 * g.row_gap;
+                    if *height > spanned {
+                        heights[row + span - 1] += height - spanned;
+                    }
+                }
+
+                let tops = heights
+                    .iter()
+                    .scan(top_left.y, |top, height| {
+                        let row_top = *top;
+                        *top += height + g.row_gap;
+                        Some(row_top)
+                    })
+                    .collect::<Vec<f32>>();
+                for (row, _, _, boxes) in cells {
+                    let offset = tops[row] - top_left.y;
+                    textbox_positions.extend(
+                        boxes
+                            .into_iter()
+                            .map(|(textbox, element)| (textbox.move_y_by(offset), element)),
+                    );
+                }
+
+                let gaps = heights.len().saturating_sub(1) as f32 * g.row_gap;
+                top_left.y + heights.iter().sum::<f32>() + gaps
+            }
             SectionLayout::Ref(_) => {
                 todo!("Should not be able to compute textbox positions of uninstantiated layout")
             }
+        };
+
+        if let Some(index) = decoration {
+            let width = self.width().get_fixed_unchecked();
+            textbox_positions[index].0 =
+                SpatialBox::new(top_left, Point::new(top_left.x + width, depth));
+        }
+
+        depth
+    }
+
+    // Distance from the top of the layout to the baseline of its first line
+    fn baseline(&self, font_dict: &FontDict) -> f32 {
+        match self {
+            SectionLayout::Stack(c) => {
+                c.inset() + c.elements.first().map_or(0.0, |e| e.baseline(font_dict))
+            }
+            SectionLayout::FlexRow(c) => {
+                c.inset()
+                    + c.elements
+                        .iter()
+                        .map(|e| e.baseline(font_dict))
+                        .fold(0.0, f32::max)
+            }
+            SectionLayout::Text(e) => e.font().get_baseline(font_dict),
+            // Rules sit on the baseline of the row
+            SectionLayout::Rule(r) => r.height(),
+            SectionLayout::Image(i) => i.height,
+            SectionLayout::Icon(i) => i.font().get_baseline(font_dict),
+            // Meters sit on the baseline of the row, like rules
+            SectionLayout::Meter(m) => m.size,
+            SectionLayout::Timeline(t) => {
+                let body = t.elements.first().map_or(0.0, |e| e.baseline(font_dict));
+                t.date.baseline(font_dict).max(body)
+            }
+            SectionLayout::Grid(g) => g
+                .cells
+                .first()
+                .map_or(0.0, |c| c.element.baseline(font_dict)),
+            SectionLayout::FrozenRow(_) | SectionLayout::Ref(_) => {
+                unreachable!("Cannot compute the baseline of {}", self.type_())
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, font::FontLoader, shape::Shape};
+
+    fn font_dict() -> FontDict {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+        font_dict
+    }
+
+    #[test]
+    fn test_vertical_alignment() {
+        let font_dict = font_dict();
+
+        let row = |vertical_alignment: &str| {
+            let json = format!(
+                r#"{{
+                    "FlexRow": {{
+                        "elements": [
+                            {{ "Text": {{ "item": "Company", "font": {{ "size": 14.0 }} }} }},
+                            {{ "Text": {{ "item": "2021", "font": {{ "size": 12.0 }} }} }}
+                        ],
+                        "height": "40pt",
+                        "vertical_alignment": "{}"
+                    }}
+                }}"#,
+                vertical_alignment
+            );
+            let layout: SectionLayout = serde_json::from_str(&json).unwrap();
+            layout
+                .instantiate(&HashMap::new())
+                .normalize(500.0, &font_dict)
+                .compute_boxes(&font_dict)
+        };
+
+        let boxes = row("Baseline");
+        let (large, small) = (&boxes.elements[0], &boxes.elements[1]);
+        let baseline = |(textbox, element): &(SpatialBox, Element)| {
+            textbox.top_left.y + element.font().get_baseline(&font_dict)
+        };
+        assert!((baseline(large) - baseline(small)).abs() < 0.001);
+        assert!(small.0.top_left.y > large.0.top_left.y);
+        assert_eq!(boxes.bounding_box.height(), 40.0);
+
+        let boxes = row("Bottom");
+        for (textbox, _) in boxes.elements.iter() {
+            assert!((textbox.bottom_right.y - 40.0).abs() < 0.001);
+        }
+        // The height of a row that wraps is kept by the stack of its lines
+        let json = r#"{
+            "FlexRow": {
+                "elements": [
+                    { "Text": { "item": "Company", "font": { "size": 14.0 } } },
+                    { "Text": { "item": "2021", "font": { "size": 12.0 } } }
+                ],
+                "height": "80pt",
+                "vertical_alignment": "Bottom"
+            }
+        }"#;
+        let layout: SectionLayout = serde_json::from_str(json).unwrap();
+        let boxes = layout
+            .instantiate(&HashMap::new())
+            .normalize(60.0, &font_dict)
+            .compute_boxes(&font_dict);
+        let (first, last) = (&boxes.elements[0].0, &boxes.elements.last().unwrap().0);
+        assert!(last.top_left.y > first.top_left.y);
+        assert!((last.bottom_right.y - 80.0).abs() < 0.001);
+        assert_eq!(boxes.bounding_box.height(), 80.0);
+    }
+
+    #[test]
+    fn test_gap_and_line_height() {
+        let font_dict = font_dict();
+        let json = r#"{
+            "Stack": {
+                "elements": [
+                    {
+                        "FlexRow": {
+                            "elements": [
+                                { "Text": { "item": "Coq" } },
+                                { "Text": { "item": "OCaml" } }
+                            ],
+                            "gap": 10.0
+                        }
+                    },
+                    { "Text": { "item": "Extraction", "font": { "line_height": 1.5 } } }
+                ],
+                "gap": 4.0
+            }
+        }"#;
+        let layout: SectionLayout = serde_json::from_str(json).unwrap();
+        let boxes = layout
+            .instantiate(&HashMap::new())
+            .normalize(500.0, &font_dict)
+            .compute_boxes(&font_dict);
+
+        let (coq, ocaml, extraction) = (
+            &boxes.elements[0].0,
+            &boxes.elements[1].0,
+            &boxes.elements[2].0,
+        );
+        let height = Font::default().get_height(&font_dict);
+
+        assert!((ocaml.top_left.x - (coq.bottom_right.x + 10.0)).abs() < 0.001);
+        assert!((extraction.top_left.y - (height + 4.0)).abs() < 0.001);
+        assert!((extraction.height() - height * 1.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_background() {
+        let font_dict = font_dict();
+        let json = r##"{
+            "Stack": {
+                "elements": [
+                    { "Text": { "item": "Skills", "font": { "color": "steelblue" } } },
+                    { "Text": { "item": "Rust", "color": "#fff", "background": "rgb(0, 0, 0)" } }
+                ],
+                "background": "#eeeeee"
+            }
+        }"##;
+        let layout: SectionLayout = serde_json::from_str(json).unwrap();
+        let boxes = layout
+            .instantiate(&HashMap::new())
+            .normalize(500.0, &font_dict)
+            .compute_boxes(&font_dict);
+
+        // The background of the stack comes first and covers all of it
+        let (background, element) = &boxes.elements[0];
+        assert_eq!(
+            element.shape,
+            Some(Shape::Rectangle {
+                fill: Some(Color::rgb(238, 238, 238)),
+                border: None
+            })
+        );
+        assert_eq!(background.width(), 500.0);
+        assert_eq!(background.height(), boxes.bounding_box.height());
+
+        let (_, skills) = &boxes.elements[1];
+        assert_eq!(skills.text_color(), Color::rgb(70, 130, 180));
+        let (_, rust) = &boxes.elements[2];
+        assert_eq!(rust.text_color(), Color::WHITE);
+        assert_eq!(rust.background, Some(Color::BLACK));
+    }
+
+    #[test]
+    fn test_rule_and_border() {
+        let font_dict = font_dict();
+        let json = r##"{
+            "Stack": {
+                "elements": [
+                    { "Text": { "item": "Experience" } },
+                    { "Rule": { "thickness": 0.5, "spacing": 3, "style": "Dashed" } }
+                ],
+                "border": { "width": 2, "color": "gray", "radius": 4 }
+            }
+        }"##;
+        let layout: SectionLayout = serde_json::from_str(json).unwrap();
+        let boxes = layout
+            .instantiate(&HashMap::new())
+            .normalize(500.0, &font_dict)
+            .compute_boxes(&font_dict);
+
+        let (border, element) = &boxes.elements[0];
+        assert!(matches!(
+            element.shape,
+            Some(Shape::Rectangle { fill: None, border: Some(b) }) if b.radius == 4.0
+        ));
+
+        // The elements are laid out inside of the border
+        let (text, _) = &boxes.elements[1];
+        assert_eq!((text.top_left.x, text.top_left.y), (2.0, 2.0));
+
+        let (rule, element) = &boxes.elements[2];
+        assert_eq!(rule.width(), 496.0);
+        assert_eq!(rule.height(), 6.5);
+        assert_eq!(rule.top_left.y, text.bottom_right.y);
+        assert!(matches!(element.shape, Some(Shape::Line { thickness, .. }) if thickness == 0.5));
+
+        assert_eq!(border.height(), rule.bottom_right.y + 2.0);
+    }
+
+    #[test]
+    fn test_timeline() {
+        let font_dict = font_dict();
+        let json = r#"{
+            "Timeline": {
+                "date": { "Ref": { "item": "Date", "font": { "size": 10.0 } } },
+                "elements": [
+                    { "Ref": { "item": "Title", "font": { "size": 14.0 } } },
+                    { "Ref": { "item": "Company" } }
+                ]
+            }
+        }"#;
+        let layout: SectionLayout = serde_json::from_str(json).unwrap();
+        let section = [
+            ("Date".to_string(), ItemContent::String("2021".to_string())),
+            (
+                "Title".to_string(),
+                ItemContent::String("Engineer".to_string()),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let boxes = layout
+            .instantiate(&section)
+            .normalize(500.0, &font_dict)
+            .compute_boxes(&font_dict);
+
+        let (date, date_element) = &boxes.elements[1];
+        let (title, title_element) = &boxes.elements[2];
+        assert_eq!(date.top_left.x, 0.0);
+        assert_eq!(title.top_left.x, 94.0);
+        let baseline = title.top_left.y + title_element.font().get_baseline(&font_dict);
+        assert_eq!(
+            date.top_left.y + date_element.font().get_baseline(&font_dict),
+            baseline
+        );
+
+        // The marker stands on the shared baseline, the spine runs through the whole entry
+        let (marker, element) = &boxes.elements[3];
+        assert!(matches!(element.shape, Some(Shape::Dot { .. })));
+        assert_eq!(marker.bottom_right.y, baseline);
+        assert_eq!((marker.top_left.x + marker.bottom_right.x) / 2.0, 83.0);
+
+        let (spine, element) = &boxes.elements[0];
+        assert!(matches!(
+            element.shape,
+            Some(Shape::Line { vertical: true, .. })
+        ));
+        assert_eq!(spine.top_left.y, 0.0);
+        assert_eq!(spine.bottom_right.y, boxes.bounding_box.height());
+        assert_eq!(boxes.bounding_box.height(), title.bottom_right.y);
+    }
+
+    #[test]
+    fn test_grid() {
+        let font_dict = font_dict();
+        let json = r#"{
+            "Grid": {
+                "columns": ["auto", "1fr", "50pt"],
+                "column_gap": 10,
+                "cells": [
+                    { "element": { "Ref": { "item": "Date" } } },
+                    { "element": { "Ref": { "item": "Title" } } },
+                    { "element": { "Text": { "item": "Remote" } } },
+                    { "element": { "Ref": { "item": "Summary" } }, "column_span": 3 }
+                ]
+            }
+        }"#;
+        let layout: SectionLayout = serde_json::from_str(json).unwrap();
+        let items = ["2021", "March 2019 - 2021"]
+            .iter()
+            .map(|date| {
+                let item = [
+                    ("Date".to_string(), ItemContent::String(date.to_string())),
+                    (
+                        "Title".to_string(),
+                        ItemContent::String("Engineer".to_string()),
+                    ),
+                    (
+                        "Summary".to_string(),
+                        ItemContent::String("Rust".to_string()),
+                    ),
+                ]
+                .into_iter()
+                .collect();
+                layout.instantiate(&item).scale_width(500.0)
+            })
+            .collect::<Vec<SectionLayout>>();
+
+        let mut widths = HashMap::new();
+        items
+            .iter()
+            .for_each(|item| item.measure_grids(&font_dict, &mut widths));
+        let date_width = Font::default().get_width("March 2019 - 2021", &font_dict);
+
+        // Both items place their titles after the longest date of the section
+        for item in items.iter() {
+            let boxes = item
+                .with_section_widths(&widths)
+                .normalize(500.0, &font_dict)
+                .compute_boxes(&font_dict);
+            let (date, _) = &boxes.elements[0];
+            let (title, _) = &boxes.elements[1];
+            let (remote, _) = &boxes.elements[2];
+            let (summary, _) = &boxes.elements[3];
+            assert_eq!(title.top_left.x, date_width + 10.0);
+            assert_eq!(remote.top_left.x, 450.0);
+            assert_eq!(summary.top_left.x, 0.0);
+            assert_eq!(summary.top_left.y, date.bottom_right.y);
+        }
+
+        // Without the section, auto columns fit their own cells
+        let boxes = items[0]
+            .normalize(500.0, &font_dict)
+            .compute_boxes(&font_dict);
+        let (title, _) = &boxes.elements[1];
+        assert!(title.top_left.x < date_width);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let font_dict = font_dict();
+        let row = |overflow: &str| {
+            let json = format!(
+                r#"{{
+                    "FrozenRow": {{
+                        "elements": [
+                            {{ "Ref": {{ "item": "Title" }} }},
+                            {{ "Ref": {{ "item": "Date" }} }}
+                        ],
+                        "width": "150pt",
+                        "overflow": "{}"
+                    }}
+                }}"#,
+                overflow
+            );
+            let layout: SectionLayout = serde_json::from_str(&json).unwrap();
+            let item = [
+                (
+                    "Title".to_string(),
+                    ItemContent::String("Engineer".to_string()),
+                ),
+                (
+                    "Date".to_string(),
+                    ItemContent::String("From the first of March 2019 to today".to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect();
+            layout
+                .instantiate(&item)
+                .normalize_with_diagnostics(500.0, &font_dict)
+        };
+
+        let (layout, diagnostics) = row("Wrap");
+        assert!(matches!(layout, SectionLayout::Stack(_)));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].field, "Title, Date");
+        assert!(diagnostics[0].message.ends_with("its elements are wrapped"));
+
+        for overflow in ["Shrink", "Ellipsis", "Clip"] {
+            let (layout, diagnostics) = row(overflow);
+            assert_eq!(diagnostics.len(), 1, "{}", overflow);
+            let boxes = layout.compute_boxes(&font_dict);
+            // The row keeps to a single line within its width
+            assert_eq!(boxes.elements.len(), 2, "{}", overflow);
+            let (date, element) = &boxes.elements[1];
+            assert!(date.bottom_right.x <= 150.01, "{}", overflow);
+            match overflow {
+                "Shrink" => assert!(element.font().size < 12.0),
+                "Ellipsis" => assert!(element.item.ends_with('…')),
+                _ => assert!(element.item.starts_with("From the")),
+            }
+        }
+
+        // Text on its own is fitted by its own policy
+        let layout: SectionLayout = serde_json::from_str(
+            r#"{ "Ref": { "item": "Date", "width": "40pt", "overflow": "Ellipsis" } }"#,
+        )
+        .unwrap();
+        let item = [(
+            "Date".to_string(),
+            ItemContent::String("March 2019".to_string()),
+        )]
+        .into_iter()
+        .collect();
+        let (layout, diagnostics) = layout
+            .instantiate(&item)
+            .normalize_with_diagnostics(500.0, &font_dict);
+        assert_eq!(
+            diagnostics[0].in_section("Experience").to_string(),
+            format!("Experience, Date: {}", diagnostics[0].message)
+        );
+        let boxes = layout.compute_boxes(&font_dict);
+        assert_eq!(boxes.elements.len(), 1);
+        assert!(boxes.elements[0].1.item.ends_with('…'));
+    }
+}
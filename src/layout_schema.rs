@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{font::Font, layout::SectionLayout};
+use crate::{
+    font::Font,
+    hyphenation::Hyphenation,
+    layout::SectionLayout,
+    style::{Style, Styles},
+};
 
 pub trait Named {
     fn name(&self) -> &str;
@@ -11,6 +16,12 @@ pub struct LayoutSchema {
     pub schema_name: String,
     pub header_layout_schema: SectionLayout,
     pub item_layout_schema: SectionLayout,
+    // Hyphenation of the elements that do not have a setting of their own
+    #[serde(default = "Option::default")]
+    pub hyphenation: Option<Hyphenation>,
+    // Styles the layouts refer to by name, they win over the document-wide ones
+    #[serde(default = "Styles::new")]
+    pub styles: Styles,
 }
 
 impl Named for LayoutSchema {
@@ -25,6 +36,34 @@ impl LayoutSchema {
         schema
     }
 
+    // Adds the document-wide font fallbacks to every font of the schema
+    pub fn inherit_font_fallbacks(&self, fallbacks: &[String]) -> LayoutSchema {
+        LayoutSchema {
+            schema_name: self.schema_name.clone(),
+            header_layout_schema: self.header_layout_schema.inherit_font_fallbacks(fallbacks),
+            item_layout_schema: self.item_layout_schema.inherit_font_fallbacks(fallbacks),
+            hyphenation: self.hyphenation.clone(),
+            styles: self.styles.clone(),
+        }
+    }
+
+    // Replaces the style references of the layouts with the properties of the styles
+    pub fn resolve_styles(&self, document_styles: &Styles) -> LayoutSchema {
+        let mut styles = document_styles.clone();
+        styles.extend(self.styles.clone());
+        LayoutSchema {
+            schema_name: self.schema_name.clone(),
+            header_layout_schema: self
+                .header_layout_schema
+                .resolve_styles(&styles, &Style::default()),
+            item_layout_schema: self
+                .item_layout_schema
+                .resolve_styles(&styles, &Style::default()),
+            hyphenation: self.hyphenation.clone(),
+            styles: self.styles.clone(),
+        }
+    }
+
     pub fn fonts(&self) -> Vec<Font> {
         [
             self.item_layout_schema.fonts(),
@@ -37,6 +76,11 @@ impl LayoutSchema {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        alignment::Alignment,
+        font::{FontWeight, PartialFont},
+        margin::Margin,
+    };
     use expect_test::expect;
 
     #[test]
@@ -287,4 +331,132 @@ mod tests {
 "#]];
         expected.assert_debug_eq(&actual);
     }
+
+    #[test]
+    fn test_resolve_styles() {
+        let json = r##"[{
+            "schema_name": "Work",
+            "styles": {
+                "title": { "font": { "name": "Exo", "size": 14.0, "weight": "Bold" } },
+                "muted": { "color": "#777777", "alignment": "Right" }
+            },
+            "header_layout_schema": { "Text": { "item": "Work", "style": "heading" } },
+            "item_layout_schema": {
+                "Stack": {
+                    "style": "body",
+                    "elements": [
+                        { "Ref": { "item": "Company" } },
+                        { "Ref": { "item": "Position", "style": "title" } },
+                        { "Ref": { "item": "Date", "style": "muted", "font": { "size": 9.0 } } },
+                        { "Ref": { "item": "Team", "style": "title", "font": { "size": 11.0 } } },
+                        { "Ref": { "item": "Location", "style": "muted", "alignment": "Left" } }
+                    ]
+                }
+            }
+        }]"##;
+        let mut document_styles = Styles::new();
+        let body = Style {
+            font: PartialFont {
+                size: Some(10.0),
+                ..PartialFont::default()
+            },
+            margin: Some(Margin::new(2.0, 2.0, 0.0, 0.0)),
+            ..Style::default()
+        };
+        document_styles.insert("body".to_string(), body);
+        document_styles.insert("heading".to_string(), Style::default());
+
+        let schema = LayoutSchema::from_json(json)[0].resolve_styles(&document_styles);
+        let SectionLayout::Stack(stack) = &schema.item_layout_schema else {
+            panic!("the item layout is not a stack");
+        };
+        // The margin of a style stays with the container, its font cascades
+        assert_eq!(stack.margin().top, 2.0);
+        let elements = stack
+            .elements
+            .iter()
+            .map(|e| match e {
+                SectionLayout::Ref(e) => e.clone(),
+                _ => panic!("the elements are not refs"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(elements[0].font().size, 10.0);
+        assert_eq!(elements[0].margin().top, 0.0);
+        assert_eq!(elements[1].font().name, "Exo");
+        assert_eq!(elements[1].font().size, 14.0);
+        // Properties set on the element win over its style
+        assert_eq!(elements[2].font().size, 9.0);
+        assert_eq!(elements[2].color.unwrap().r, 0x77);
+        assert_eq!(elements[2].alignment(), Alignment::Right);
+        // Fonts are merged property by property
+        let team = elements[3].font();
+        assert_eq!(team.name, "Exo");
+        assert_eq!(team.weight, FontWeight(700));
+        assert_eq!(team.size, 11.0);
+        // Properties written with their default value still win over the style
+        assert_eq!(elements[4].alignment(), Alignment::Left);
+    }
+
+    #[test]
+    fn test_resolve_styles_of_shapes() {
+        let json = r##"[{
+            "schema_name": "Skills",
+            "styles": {
+                "title": { "font": { "name": "Exo", "size": 14.0 }, "color": "#111111" }
+            },
+            "header_layout_schema": { "Text": { "item": "Skills" } },
+            "item_layout_schema": {
+                "Stack": {
+                    "style": "accent",
+                    "elements": [
+                        { "Icon": { "source": { "Name": "Code" } } },
+                        { "Icon": { "source": { "Name": "Link" }, "style": "title" } },
+                        { "Rule": {} },
+                        { "Meter": { "value": { "Ref": "Level" } } },
+                        { "Meter": { "value": { "Ref": "Level" }, "color": "#00FF00" } }
+                    ]
+                }
+            }
+        }]"##;
+        let mut theme_styles = Styles::new();
+        let accent = Style {
+            font: PartialFont {
+                size: Some(10.0),
+                ..PartialFont::default()
+            },
+            color: Some("#1F6FEB".parse().unwrap()),
+            ..Style::default()
+        };
+        theme_styles.insert("accent".to_string(), accent);
+
+        let schema = LayoutSchema::from_json(json)[0].resolve_styles(&theme_styles);
+        let SectionLayout::Stack(stack) = &schema.item_layout_schema else {
+            panic!("the item layout is not a stack");
+        };
+        let icon = |index: usize| match &stack.elements[index] {
+            SectionLayout::Icon(icon) => icon.clone(),
+            _ => panic!("element {} is not an icon", index),
+        };
+        let meter = |index: usize| match &stack.elements[index] {
+            SectionLayout::Meter(meter) => meter.clone(),
+            _ => panic!("element {} is not a meter", index),
+        };
+        let SectionLayout::Rule(rule) = &stack.elements[2] else {
+            panic!("element 2 is not a rule");
+        };
+        let (code, link) = (icon(0), icon(1));
+        let (meter, green) = (meter(3), meter(4));
+
+        // The font and the color of the container cascade to icons, rules and meters
+        assert_eq!(code.font().size, 10.0);
+        assert_eq!(code.color.unwrap().b, 0xEB);
+        assert_eq!(rule.color().b, 0xEB);
+        assert_eq!(meter.color().b, 0xEB);
+        // Icons refer to styles like text, and colors set on a node win over the cascade
+        assert_eq!(link.font().name, "Exo");
+        assert_eq!(link.font().size, 14.0);
+        assert_eq!(link.color.unwrap().b, 0x11);
+        assert_eq!(green.color().g, 0xFF);
+        assert_eq!(green.color().b, 0x00);
+    }
 }
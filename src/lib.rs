@@ -1,19 +1,38 @@
-#![feature(file_create_new)]
 mod alignment;
 mod any_layout;
 mod basic_layout;
+mod color;
 mod container;
 mod data_schema;
+mod diagnostic;
+mod direction;
 mod element;
 pub mod font;
+pub mod font_registry;
+mod font_subset;
+mod grid;
+mod height;
+mod hyphenation;
+mod icon;
+mod image_element;
 mod layout;
 pub mod layout_schema;
 pub mod local_storage;
 mod margin;
+mod meter;
+mod overflow;
 pub mod pdf_layout;
 pub mod png_layout;
 mod point;
 pub mod resume_data;
 mod resume_layout;
+mod shape;
+mod shaping;
 mod spatial_box;
+mod style;
+mod text_transform;
+mod theme;
+mod timeline;
+mod unit;
+mod variable_font;
 mod width;
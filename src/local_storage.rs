@@ -1,15 +1,15 @@
 extern crate directories;
 
 use std::{
-    io::Write,
+    io::{Error, ErrorKind, Write},
     path::{Path, PathBuf},
 };
 
 use directories::ProjectDirs;
 
 use crate::{
-    data_schema::DataSchema, layout_schema::LayoutSchema, resume_data::ResumeData,
-    resume_layout::ResumeLayout,
+    data_schema::DataSchema, font_registry::BUNDLED_FONTS, layout_schema::LayoutSchema,
+    resume_data::ResumeData, resume_layout::ResumeLayout, theme::Theme,
 };
 
 /// This module provides the abstractions for interacting with persistent storage.
@@ -20,22 +20,30 @@ use crate::{
 ///        ├── resumes
 ///             ├── resume1.json
 ///             ├── resume2.json
+///        ├── fonts
+///             ├── Exo
 ///        ├── data-schemas.json
 ///        ├── layout-schemas.json
-///        |── resume-layouts.json
+///        ├── resume-layouts.json
+///        |── themes.json
 ///
 /// The resume.json files contain the resume information, as well as references to the
 /// schema names.
 ///
-/// This module provides 3 types of functionalities for all 4 data types:
+/// This module provides 3 types of functionalities for all 5 data types:
 ///     1. List
 ///     2. Load
 ///     3. Save
+pub struct LocalStorage {
+    dir: String,
+}
 
 // Initiation Function
 
-pub struct LocalStorage {
-    dir: String,
+impl Default for LocalStorage {
+    fn default() -> LocalStorage {
+        LocalStorage::new()
+    }
 }
 
 impl LocalStorage {
@@ -55,6 +63,15 @@ impl LocalStorage {
             dir: dir.to_string(),
         }
     }
+
+    pub fn dir(&self) -> &Path {
+        Path::new(self.dir.as_str())
+    }
+
+    // Local fonts placed here are found regardless of the working directory
+    pub fn fonts_dir(&self) -> PathBuf {
+        self.dir().join("fonts")
+    }
 }
 
 impl LocalStorage {
@@ -62,6 +79,16 @@ impl LocalStorage {
         let data_dir = Path::new(self.dir.as_str());
         // Create data_dir/resumes if it does not exist
         std::fs::create_dir_all(data_dir.join("resumes")).unwrap();
+        // Create data_dir/fonts if it does not exist
+        std::fs::create_dir_all(self.fonts_dir()).unwrap();
+        // Install the bundled fonts into data_dir/fonts if they are not there
+        for (path, data) in BUNDLED_FONTS {
+            let path = self.fonts_dir().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            if let Ok(mut font_file) = std::fs::File::create_new(path) {
+                font_file.write_all(data).unwrap();
+            }
+        }
         // Create data_dir/data-schemas.json if it does not exist
         if let Ok(mut data_schemas_file) =
             std::fs::File::create_new(data_dir.join("data-schemas.json"))
@@ -80,6 +107,12 @@ impl LocalStorage {
         {
             resume_layouts_file.write_all("[]".as_bytes()).unwrap();
         }
+        // Create data_dir/themes.json with the bundled themes if it does not exist
+        if let Ok(mut themes_file) = std::fs::File::create_new(data_dir.join("themes.json")) {
+            themes_file
+                .write_all(include_str!("../data/themes.json").as_bytes())
+                .unwrap();
+        }
     }
 }
 
@@ -132,6 +165,16 @@ impl LocalStorage {
             .map(|schema| schema.schema_name.to_string())
             .collect()
     }
+
+    pub fn list_themes(&self) -> Vec<String> {
+        let data_dir = Path::new(self.dir.as_str());
+        let file = std::fs::File::open(data_dir.join("themes.json")).unwrap();
+        let themes: Vec<Theme> = serde_json::from_reader(file).unwrap();
+        themes
+            .iter()
+            .map(|theme| theme.schema_name.to_string())
+            .collect()
+    }
 }
 
 // Loading Functions
@@ -174,6 +217,37 @@ impl LocalStorage {
             .find(|schema| schema.schema_name == schema_name)
             .unwrap()
     }
+
+    pub fn load_theme(&self, theme_name: &str) -> std::io::Result<Theme> {
+        let data_dir = Path::new(self.dir.as_str());
+        let file = std::fs::File::open(data_dir.join("themes.json"))?;
+        let themes: Vec<Theme> = serde_json::from_reader(file)?;
+        let names = themes
+            .iter()
+            .map(|theme| theme.schema_name.to_string())
+            .collect::<Vec<String>>();
+        themes
+            .into_iter()
+            .find(|theme| theme.schema_name == theme_name)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "Theme {} is not found, available themes are: [{}]",
+                        theme_name,
+                        names.join(", ")
+                    ),
+                )
+            })
+    }
+
+    // The theme the resume is rendered in, resumes without one are rendered without roles
+    pub fn load_resume_theme(&self, resume_data: &ResumeData) -> std::io::Result<Theme> {
+        match &resume_data.theme {
+            Some(theme) => self.load_theme(theme),
+            None => Ok(Theme::default()),
+        }
+    }
 }
 
 // Saving Functions
@@ -232,4 +306,20 @@ impl LocalStorage {
         let file = std::fs::File::create(data_dir.join("resume-layouts.json")).unwrap();
         serde_json::to_writer_pretty(file, &resume_layouts).unwrap();
     }
+
+    pub fn save_theme(&self, theme: &Theme) {
+        let data_dir = Path::new(self.dir.as_str());
+        let file = std::fs::File::open(data_dir.join("themes.json")).unwrap();
+        let mut themes: Vec<Theme> = serde_json::from_reader(file).unwrap();
+        if let Some(index) = themes
+            .iter()
+            .position(|t| t.schema_name == theme.schema_name)
+        {
+            themes[index] = theme.clone();
+        } else {
+            themes.push(theme.clone());
+        }
+        let file = std::fs::File::create(data_dir.join("themes.json")).unwrap();
+        serde_json::to_writer_pretty(file, &themes).unwrap();
+    }
 }
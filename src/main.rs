@@ -1,23 +1,41 @@
-#![feature(file_create_new)]
-
 use crate::{layout_schema::LayoutSchema, local_storage::LocalStorage};
 mod alignment;
 mod any_layout;
 mod basic_layout;
+mod color;
 mod container;
 mod data_schema;
+mod diagnostic;
+mod direction;
 mod element;
 mod font;
+mod font_registry;
+mod font_subset;
+mod grid;
+mod height;
+mod hyphenation;
+mod icon;
+mod image_element;
 mod layout;
 mod layout_schema;
 mod local_storage;
 mod margin;
+mod meter;
+mod overflow;
 mod pdf_layout;
 mod png_layout;
 mod point;
 mod resume_data;
 mod resume_layout;
+mod shape;
+mod shaping;
 mod spatial_box;
+mod style;
+mod text_transform;
+mod theme;
+mod timeline;
+mod unit;
+mod variable_font;
 mod width;
 
 fn main() {
@@ -27,6 +45,7 @@ fn main() {
     println!("{:?}", ls.list_data_schemas());
     println!("{:?}", ls.list_layout_schemas());
     println!("{:?}", ls.list_resume_layouts());
+    println!("{:?}", ls.list_themes());
     println!("{:?}", ls.list_resumes());
 
     let resume_data = ls.load_resume("resume2");
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    color::Color, font::Font, point::Point, resume_data::ItemContent, shape::Shape,
+    spatial_box::SpatialBox, style::Style, width::Width,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum MeterStyle {
+    // A progress bar across the width of the meter
+    #[default]
+    Bar,
+    // One dot or star per unit of the maximum
+    Dots,
+    Stars,
+}
+
+/// The value of a meter, given in the layout or read from a `Number` field of the section.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MeterValue {
+    Value(f32),
+    Ref(String),
+}
+
+/// Draws a value out of `max` as a bar, dots or stars, such as the level of a skill.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Meter {
+    #[serde(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uid: Uuid,
+    pub value: MeterValue,
+    #[serde(default = "Meter::default_max")]
+    pub max: f32,
+    #[serde(default = "MeterStyle::default")]
+    pub style: MeterStyle,
+    // Width of bars, dots and stars are as wide as they need to be
+    #[serde(default = "Width::default")]
+    pub width: Width,
+    // Height of the bar, or the diameter of the dots and the stars
+    #[serde(default = "Meter::default_size")]
+    pub size: f32,
+    // Color of the filled marks, falls back to the color cascading from the containers
+    // above the meter
+    #[serde(default = "Option::default")]
+    pub color: Option<Color>,
+    #[serde(default = "Meter::default_empty_color")]
+    pub empty_color: Color,
+}
+
+impl Meter {
+    pub fn default_max() -> f32 {
+        5.0
+    }
+
+    pub fn default_size() -> f32 {
+        8.0
+    }
+
+    pub fn default_empty_color() -> Color {
+        Color::rgb(211, 211, 211)
+    }
+
+    pub fn color(&self) -> Color {
+        self.color.unwrap_or_default()
+    }
+
+    pub fn is_instantiated(&self) -> bool {
+        matches!(self.value, MeterValue::Value(_))
+    }
+
+    // Meters referring to a missing or non numeric field are left out
+    pub fn instantiate(&self, section: &HashMap<String, ItemContent>) -> Option<Meter> {
+        let value = match &self.value {
+            MeterValue::Value(value) => *value,
+            MeterValue::Ref(field) => match section.get(field)? {
+                ItemContent::Number(n) => *n,
+                content => content.to_string().trim().parse::<f32>().ok()?,
+            },
+        };
+
+        Some(self.with_value(MeterValue::Value(value)))
+    }
+
+    pub fn with_value(&self, value: MeterValue) -> Meter {
+        Meter {
+            uid: self.uid,
+            value,
+            max: self.max,
+            style: self.style,
+            width: self.width,
+            size: self.size,
+            color: self.color,
+            empty_color: self.empty_color,
+        }
+    }
+
+    pub fn with_width(&self, width: Width) -> Meter {
+        Meter {
+            uid: self.uid,
+            value: self.value.clone(),
+            max: self.max,
+            style: self.style,
+            width,
+            size: self.size,
+            color: self.color,
+            empty_color: self.empty_color,
+        }
+    }
+
+    pub fn with_style(&self, style: &Style) -> Meter {
+        Meter {
+            uid: self.uid,
+            value: self.value.clone(),
+            max: self.max,
+            style: self.style,
+            width: self.width,
+            size: self.size,
+            color: self.color.or(style.color),
+            empty_color: self.empty_color,
+        }
+    }
+
+    // Number of dots or stars
+    fn count(&self) -> usize {
+        self.max.ceil().max(1.0) as usize
+    }
+
+    fn gap(&self) -> f32 {
+        self.size / 2.0
+    }
+
+    pub fn width(&self) -> Width {
+        match self.style {
+            MeterStyle::Bar => self.width,
+            MeterStyle::Dots | MeterStyle::Stars => {
+                let count = self.count() as f32;
+                Width::Absolute(count * self.size + (count - 1.0) * self.gap())
+            }
+        }
+    }
+
+    pub fn bound_width(&self, width: f32) -> Meter {
+        self.with_width(Width::Absolute(
+            self.width().bound(width).get_fixed_unchecked(),
+        ))
+    }
+
+    pub fn scale_width(&self, w: f32) -> Meter {
+        // Meters have no font, so ems are relative to the default font size
+        self.with_width(self.width().scale(w, Font::default_size()))
+    }
+
+    /// The boxes and the shapes of the marks of the meter, a single bar or one mark per
+    /// unit of the maximum, filled in proportion to the value.
+    pub fn marks(&self, top_left: Point) -> Vec<(SpatialBox, Shape)> {
+        let MeterValue::Value(value) = self.value else {
+            unreachable!("Cannot draw the meter of an uninstantiated layout")
+        };
+        let value = value.clamp(0.0, self.max);
+        let mark = |left: f32, width: f32, filled: f32| {
+            (
+                SpatialBox::new(
+                    top_left.move_x_by(left),
+                    top_left.move_x_by(left + width).move_y_by(self.size),
+                ),
+                Shape::Meter {
+                    style: self.style,
+                    filled,
+                    color: self.color(),
+                    empty_color: self.empty_color,
+                },
+            )
+        };
+
+        match self.style {
+            MeterStyle::Bar => {
+                let filled = if self.max > 0.0 {
+                    value / self.max
+                } else {
+                    0.0
+                };
+                vec![mark(0.0, self.width.get_fixed_unchecked(), filled)]
+            }
+            MeterStyle::Dots | MeterStyle::Stars => (0..self.count())
+                .map(|i| {
+                    let left = i as f32 * (self.size + self.gap());
+                    mark(left, self.size, (value - i as f32).clamp(0.0, 1.0))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Corners of a five pointed star inscribed in the box, starting from the top.
+pub fn star_points(box_: &SpatialBox) -> Vec<(f32, f32)> {
+    let (cx, cy) = (
+        (box_.top_left.x + box_.bottom_right.x) / 2.0,
+        (box_.top_left.y + box_.bottom_right.y) / 2.0,
+    );
+    let outer = box_.width().min(box_.height()) / 2.0;
+    // Ratio of the radii of a regular star
+    let inner = outer * 0.382;
+
+    (0..10)
+        .map(|i| {
+            let radius = if i % 2 == 0 { outer } else { inner };
+            let angle = std::f32::consts::PI * (i as f32 / 5.0 - 0.5);
+            (cx + radius * angle.cos(), cy + radius * angle.sin())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        let meter: Meter = serde_json::from_str(
+            r#"{ "value": { "Ref": "Level" }, "style": "Stars", "size": 10 }"#,
+        )
+        .unwrap();
+        let section = [("Level".to_string(), ItemContent::Number(3.5))]
+            .into_iter()
+            .collect();
+        let meter = meter.instantiate(&section).unwrap();
+        assert_eq!(meter.width(), Width::Absolute(70.0));
+
+        let marks = meter.marks(Point::new(0.0, 0.0));
+        let filled = marks
+            .iter()
+            .map(|(_, shape)| match shape {
+                Shape::Meter { filled, .. } => *filled,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<f32>>();
+        assert_eq!(filled, vec![1.0, 1.0, 1.0, 0.5, 0.0]);
+        assert_eq!(marks[1].0.top_left.x, 15.0);
+        assert_eq!(marks[4].0.bottom_right, Point::new(70.0, 10.0));
+
+        // Bars are filled in proportion to their maximum
+        let bar = Meter {
+            style: MeterStyle::Bar,
+            max: 10.0,
+            ..meter
+        }
+        .bound_width(100.0);
+        match bar.marks(Point::new(0.0, 0.0)).as_slice() {
+            [(box_, Shape::Meter { filled, .. })] => {
+                assert_eq!(box_.width(), 100.0);
+                assert_eq!(*filled, 0.35);
+            }
+            marks => panic!("expected a single bar, got {:?}", marks),
+        }
+
+        let section = [("Level".to_string(), ItemContent::String("high".to_string()))]
+            .into_iter()
+            .collect();
+        assert!(Meter {
+            value: MeterValue::Ref("Level".to_string()),
+            ..bar
+        }
+        .instantiate(&section)
+        .is_none());
+    }
+}
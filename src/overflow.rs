@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// What happens to text, or to the elements of a frozen row, too wide for their box.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Overflow {
+    // The font is made smaller until the text fits on a single line
+    Shrink,
+    // The text is cut and ends with an ellipsis
+    Ellipsis,
+    // The text is cut at the edge of its box
+    Clip,
+    // Text breaks into several lines and rows into several rows
+    #[default]
+    Wrap,
+}
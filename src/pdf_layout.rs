@@ -1,25 +1,363 @@
-use std::{collections::HashMap, fs, io::BufWriter, path::Path};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::BufWriter,
+    path::Path,
+};
 
-use printpdf::{Color, IndirectFontRef, Line, LinkAnnotation, Mm, PdfDocument, Rect, Rgb};
+use image::RgbaImage;
+use printpdf::{
+    lopdf::{self, content::Operation},
+    Color, ColorBits, ColorSpace, Image, ImageTransform, ImageXObject, IndirectFontRef, Line,
+    LineDashPattern, LinkAnnotation, Mm, PdfDocument, PdfLayerReference, Pt, Px, Rect, Rgb, SMask,
+    TextMatrix,
+};
 
 use crate::{
     any_layout::{AnyLayout, ElementBox},
+    color,
     data_schema::DataSchema,
-    font::{Font, FontLoadSource},
+    diagnostic::Diagnostic,
+    font::FontLoadSource,
+    font_registry::FontRegistry,
+    font_subset,
+    image_element::ImageElement,
     layout_schema::LayoutSchema,
-    local_storage::{self, LocalStorage},
+    local_storage::LocalStorage,
+    meter::{star_points, MeterStyle},
     resume_data::ResumeData,
+    shape::{LineStyle, Shape},
+    spatial_box::SpatialBox,
 };
 
 pub struct PdfLayout;
 
+// The layout engine works in points, printpdf expects millimeters
+fn mm(pt: f32) -> Mm {
+    Mm::from(Pt(pt as f64))
+}
+
+// The outline of a box with rounded corners, PDF coordinates start from the bottom of the
+// page. Corners are cubic curves, printpdf draws a curve from a point whose successor is
+// marked as a control point.
+fn rectangle(box_: &SpatialBox, radius: f32, page_height: f32) -> Vec<(printpdf::Point, bool)> {
+    let (left, right) = (box_.top_left.x, box_.bottom_right.x);
+    let (top, bottom) = (
+        page_height - box_.top_left.y,
+        page_height - box_.bottom_right.y,
+    );
+    let r = radius
+        .min(box_.width() / 2.0)
+        .min(box_.height() / 2.0)
+        .max(0.0);
+    // Distance of the control points from the ends of a quarter circle
+    let k = 0.5523 * r;
+
+    let points = if r == 0.0 {
+        vec![
+            (left, top, false),
+            (right, top, false),
+            (right, bottom, false),
+            (left, bottom, false),
+        ]
+    } else {
+        vec![
+            (left + r, top, false),
+            (right - r, top, true),
+            (right - r + k, top, true),
+            (right, top - r + k, false),
+            (right, top - r, false),
+            (right, bottom + r, true),
+            (right, bottom + r - k, true),
+            (right - r + k, bottom, false),
+            (right - r, bottom, false),
+            (left + r, bottom, true),
+            (left + r - k, bottom, true),
+            (left, bottom + r - k, false),
+            (left, bottom + r, false),
+            (left, top - r, true),
+            (left, top - r + k, true),
+            (left + r - k, top, false),
+            (left + r, top, false),
+        ]
+    };
+
+    points
+        .into_iter()
+        .map(|(x, y, control)| (printpdf::Point::new(mm(x), mm(y)), control))
+        .collect()
+}
+
+fn polygon(points: &[(f32, f32)], page_height: f32) -> Vec<(printpdf::Point, bool)> {
+    points
+        .iter()
+        .map(|(x, y)| (printpdf::Point::new(mm(*x), mm(page_height - y)), false))
+        .collect()
+}
+
+fn set_line_style(layer: &PdfLayerReference, style: LineStyle, width: f32) {
+    layer.set_outline_thickness(width as f64);
+    let pattern = match style.dash_pattern(width) {
+        Some((dash, gap)) => LineDashPattern::new(
+            0,
+            Some(dash.round().max(1.0) as i64),
+            Some(gap.round().max(1.0) as i64),
+            None,
+            None,
+            None,
+            None,
+        ),
+        None => LineDashPattern::default(),
+    };
+    layer.set_line_dash_pattern(pattern);
+}
+
+fn draw_shape(layer: &PdfLayerReference, box_: &SpatialBox, shape: &Shape, page_height: f32) {
+    match shape {
+        Shape::Rectangle { fill, border } => {
+            let radius = border.map_or(0.0, |b| b.radius);
+            if let Some(fill) = fill {
+                layer.set_fill_color(pdf_color(fill));
+                layer.add_shape(Line {
+                    points: rectangle(box_, radius, page_height),
+                    is_closed: true,
+                    has_fill: true,
+                    has_stroke: false,
+                    is_clipping_path: false,
+                });
+            }
+            if let Some(border) = border {
+                // The stroke is centered on the path, so the path is moved inside the box
+                // by half of the width of the border
+                let half = border.width / 2.0;
+                let inner = SpatialBox::new(
+                    box_.top_left.move_x_by(half).move_y_by(half),
+                    box_.bottom_right.move_x_by(-half).move_y_by(-half),
+                );
+                set_line_style(layer, border.style, border.width);
+                layer.set_outline_color(pdf_color(&border.color));
+                layer.add_shape(Line {
+                    points: rectangle(&inner, radius - half, page_height),
+                    is_closed: true,
+                    has_fill: false,
+                    has_stroke: true,
+                    is_clipping_path: false,
+                });
+            }
+        }
+        Shape::Line {
+            thickness,
+            color,
+            style,
+            vertical,
+        } => {
+            let (start, end) = if *vertical {
+                let x = (box_.top_left.x + box_.bottom_right.x) / 2.0;
+                ((x, box_.top_left.y), (x, box_.bottom_right.y))
+            } else {
+                let y = (box_.top_left.y + box_.bottom_right.y) / 2.0;
+                ((box_.top_left.x, y), (box_.bottom_right.x, y))
+            };
+            set_line_style(layer, *style, *thickness);
+            layer.set_outline_color(pdf_color(color));
+            layer.add_shape(Line {
+                points: vec![
+                    (
+                        printpdf::Point::new(mm(start.0), mm(page_height - start.1)),
+                        false,
+                    ),
+                    (
+                        printpdf::Point::new(mm(end.0), mm(page_height - end.1)),
+                        false,
+                    ),
+                ],
+                is_closed: false,
+                has_fill: false,
+                has_stroke: true,
+                is_clipping_path: false,
+            });
+        }
+        Shape::Dot { color } => {
+            layer.set_fill_color(pdf_color(color));
+            layer.add_shape(Line {
+                points: rectangle(box_, box_.width().min(box_.height()) / 2.0, page_height),
+                is_closed: true,
+                has_fill: true,
+                has_stroke: false,
+                is_clipping_path: false,
+            });
+        }
+        Shape::Icon { name, color } => match name.rasterize(*color, box_, IMAGE_SCALE) {
+            Ok((drawn, pixels)) => embed_pixels(layer, &drawn, &pixels, page_height),
+            Err(e) => log::warn!("Icon {:?} could not be drawn, {}", name, e),
+        },
+        Shape::Meter {
+            style,
+            filled,
+            color,
+            empty_color,
+        } => {
+            let outline = match style {
+                MeterStyle::Bar => rectangle(box_, box_.height() / 2.0, page_height),
+                MeterStyle::Dots => {
+                    rectangle(box_, box_.width().min(box_.height()) / 2.0, page_height)
+                }
+                MeterStyle::Stars => polygon(&star_points(box_), page_height),
+            };
+            layer.set_fill_color(pdf_color(empty_color));
+            layer.add_shape(Line {
+                points: outline.clone(),
+                is_closed: true,
+                has_fill: true,
+                has_stroke: false,
+                is_clipping_path: false,
+            });
+
+            // The filled part is drawn over the empty one, clipped to its fraction
+            if *filled > 0.0 {
+                let filled_box = SpatialBox::new(
+                    box_.top_left,
+                    box_.bottom_right
+                        .move_x_to(box_.top_left.x + filled * box_.width()),
+                );
+                layer.save_graphics_state();
+                layer.add_shape(Line {
+                    points: rectangle(&filled_box, 0.0, page_height),
+                    is_closed: true,
+                    has_fill: false,
+                    has_stroke: false,
+                    is_clipping_path: true,
+                });
+                layer.set_fill_color(pdf_color(color));
+                layer.add_shape(Line {
+                    points: outline,
+                    is_closed: true,
+                    has_fill: true,
+                    has_stroke: false,
+                    is_clipping_path: false,
+                });
+                layer.restore_graphics_state();
+            }
+        }
+    }
+    // Later outlines, such as the debug boxes, are solid and thin again
+    set_line_style(layer, LineStyle::Solid, 1.0);
+}
+
+// Images are embedded at 300 DPI
+const IMAGE_SCALE: f32 = 300.0 / 72.0;
+
+fn draw_image(
+    layer: &PdfLayerReference,
+    dir: &Path,
+    box_: &SpatialBox,
+    image: &ImageElement,
+    page_height: f32,
+) {
+    let (drawn, pixels) = match image.fit_into(dir, box_, IMAGE_SCALE) {
+        Ok(fitted) => fitted,
+        Err(e) => {
+            log::warn!("Image could not be drawn, {}", e);
+            return;
+        }
+    };
+
+    layer.save_graphics_state();
+    if let Some(radius) = image.clip_radius(box_) {
+        layer.add_shape(Line {
+            points: rectangle(box_, radius, page_height),
+            is_closed: true,
+            has_fill: false,
+            has_stroke: false,
+            is_clipping_path: true,
+        });
+    }
+    embed_pixels(layer, &drawn, &pixels, page_height);
+    layer.restore_graphics_state();
+}
+
+// Embeds the pixels of an image and draws them in `drawn`
+fn embed_pixels(
+    layer: &PdfLayerReference,
+    drawn: &SpatialBox,
+    pixels: &RgbaImage,
+    page_height: f32,
+) {
+    // The alpha channel is embedded as a soft mask, so transparent pixels show what is
+    // drawn below the image
+    let image_data = pixels.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+    let smask = SMask {
+        width: pixels.width() as i64,
+        height: pixels.height() as i64,
+        interpolate: true,
+        bits_per_component: 8,
+        // printpdf takes the values of the mask from `matte`
+        matte: pixels.pixels().map(|p| p[3] as i64).collect(),
+    };
+
+    let xobject = ImageXObject {
+        width: Px(pixels.width() as usize),
+        height: Px(pixels.height() as usize),
+        color_space: ColorSpace::Rgb,
+        bits_per_component: ColorBits::Bit8,
+        interpolate: true,
+        image_data,
+        image_filter: None,
+        clipping_bbox: None,
+        smask: Some(smask),
+    };
+    // At 72 DPI a pixel is a point, the pixels are then scaled to the drawn size
+    Image::from(xobject).add_to_layer(
+        layer.clone(),
+        ImageTransform {
+            translate_x: Some(mm(drawn.top_left.x)),
+            translate_y: Some(mm(page_height - drawn.bottom_right.y)),
+            scale_x: Some((drawn.width() / pixels.width() as f32) as f64),
+            scale_y: Some((drawn.height() / pixels.height() as f32) as f64),
+            dpi: Some(72.0),
+            ..ImageTransform::default()
+        },
+    );
+}
+
+// Glyphs are written by their ids, and the ToUnicode map of an embedded font only covers
+// glyphs in its cmap. Ligatures, small caps and contextual forms are not, so each shaping
+// cluster is marked with the text it was shaped from to keep the text extractable.
+fn begin_actual_text(layer: &PdfLayerReference, text: &str) {
+    let utf16be = std::iter::once(0xFEFF)
+        .chain(text.encode_utf16())
+        .flat_map(u16::to_be_bytes)
+        .collect::<Vec<u8>>();
+    let mut properties = lopdf::Dictionary::new();
+    properties.set(
+        "ActualText",
+        lopdf::Object::String(utf16be, lopdf::StringFormat::Hexadecimal),
+    );
+    layer.add_operation(Operation::new(
+        "BDC",
+        vec![
+            lopdf::Object::Name(b"Span".to_vec()),
+            lopdf::Object::Dictionary(properties),
+        ],
+    ));
+}
+
+fn end_actual_text(layer: &PdfLayerReference) {
+    layer.add_operation(Operation::new("EMC", vec![]));
+}
+
+fn pdf_color(color: &color::Color) -> Color {
+    let (r, g, b) = color.to_fractions();
+    Color::Rgb(Rgb::new(r as f64, g as f64, b as f64, None))
+}
+
 impl PdfLayout {
     pub fn render(
         local_storage: LocalStorage,
         resume_data: ResumeData,
         filepath: &Path,
         debug: bool,
-    ) -> std::io::Result<()> {
+    ) -> std::io::Result<Vec<Diagnostic>> {
         let data_schemas = resume_data
             .data_schemas()
             .iter()
@@ -33,36 +371,75 @@ impl PdfLayout {
             .collect::<Vec<LayoutSchema>>();
 
         let resume_layout = local_storage.load_resume_layout(resume_data.layout.as_str());
+        let theme = local_storage.load_resume_theme(&resume_data)?;
+
+        let page_width = resume_layout.page_width()?;
+        let page_height = resume_layout.page_height()?;
 
         let (doc, page1, layer1) = PdfDocument::new(
             "PDF_Document_title",
-            Mm(resume_layout.width as f64),
-            Mm(resume_layout.height as f64),
+            mm(page_width),
+            mm(page_height),
             "Layer 1",
         );
 
-        let (mut font_dict, pages) =
-            AnyLayout::render(&layout_schemas, &resume_data, &data_schemas, &resume_layout)
-                .unwrap();
+        let font_registry = FontRegistry::new(FontRegistry::search_paths(
+            &local_storage,
+            &resume_layout.font_paths,
+        ));
+
+        let (font_dict, pages, diagnostics) = AnyLayout::render(
+            &layout_schemas,
+            &resume_data,
+            &data_schemas,
+            &resume_layout,
+            &theme,
+            &font_registry,
+        )?;
 
         let _current_layer = doc.get_page(page1).get_layer(layer1);
 
         log::info!("Constructing printpdf font dictionary...");
 
-        let printpdf_font_dict: HashMap<String, IndirectFontRef> = font_dict
-            .iter_mut()
-            .map(|(k, v)| {
-                let pdf_font = match &v.source {
-                    FontLoadSource::Local(path) => {
-                        log::info!("Loading {} from {}", k, path);
-                        doc.add_external_font(fs::File::open(path).unwrap())
-                            .unwrap()
+        // Only the glyphs that are drawn are embedded, fonts that are not drawn at all
+        // are left out of the document
+        let mut used_glyphs: HashMap<String, BTreeSet<u16>> = HashMap::new();
+        for (box_, element) in pages.iter().flatten().flat_map(|b| b.elements.iter()) {
+            for glyph in element.shape(box_.width(), &font_dict) {
+                used_glyphs
+                    .entry(glyph.font)
+                    .or_default()
+                    .insert(glyph.glyph_id);
+            }
+        }
+
+        let printpdf_font_dict: HashMap<String, IndirectFontRef> = used_glyphs
+            .iter()
+            .map(|(k, glyphs)| {
+                let v = &font_dict[k];
+                match &v.source {
+                    FontLoadSource::Local(path) => log::info!("Loading {} from {}", k, path),
+                    FontLoadSource::System(_) => log::info!("Loading {} from the system", k),
+                };
+                // The font data is embedded rather than the file, so that variable fonts
+                // are embedded as the instance the layout was measured with
+                let font_data = match font_subset::subset(v.font_data(), glyphs) {
+                    Some(subset) => {
+                        log::info!(
+                            "Embedding {} glyphs of {} as {}+{}",
+                            glyphs.len(),
+                            k,
+                            font_subset::subset_tag(glyphs),
+                            k
+                        );
+                        subset
                     }
-                    FontLoadSource::System(font) => {
-                        let font_stream = font.copy_font_data().unwrap();
-                        doc.add_external_font(font_stream.as_slice()).unwrap()
+                    None => {
+                        log::warn!("{} can not be subset, it is embedded as a whole", k);
+                        v.font_data().to_vec()
                     }
                 };
+                let pdf_font = doc.add_external_font(font_data.as_slice()).unwrap();
 
                 (k.clone(), pdf_font)
             })
@@ -75,8 +452,8 @@ impl PdfLayout {
                 (page1, layer1)
             } else {
                 let (page, layer) = doc.add_page(
-                    Mm(resume_layout.width as f64),
-                    Mm(resume_layout.height as f64),
+                    mm(page_width),
+                    mm(page_height),
                     format!("Page {}", index + 1),
                 );
                 (page, layer)
@@ -86,6 +463,7 @@ impl PdfLayout {
                 let ElementBox {
                     bounding_box: _bounding_box,
                     elements,
+                    ..
                 } = element_box;
                 for (box_, element) in elements {
                     log::debug!(
@@ -97,37 +475,28 @@ impl PdfLayout {
                         element.item.clone()
                     );
                     let current_layer = doc.get_page(curr_page).get_layer(curr_layer);
+                    if let Some(background) = element.background {
+                        let highlight = Shape::Rectangle {
+                            fill: Some(background),
+                            border: None,
+                        };
+                        draw_shape(&current_layer, box_, &highlight, page_height);
+                    }
+                    if let Some(shape) = &element.shape {
+                        draw_shape(&current_layer, box_, shape, page_height);
+                    }
+                    if let Some(image) = &element.image {
+                        draw_image(
+                            &current_layer,
+                            local_storage.dir(),
+                            box_,
+                            image,
+                            page_height,
+                        );
+                    }
+
                     if debug {
-                        let points: Vec<(printpdf::Point, bool)> = vec![
-                            (
-                                printpdf::Point::new(
-                                    Mm(box_.top_left.x.into()),
-                                    Mm((resume_layout.height - box_.top_left.y).into()),
-                                ),
-                                false,
-                            ),
-                            (
-                                printpdf::Point::new(
-                                    Mm(box_.bottom_right.x.into()),
-                                    Mm((resume_layout.height - box_.top_left.y).into()),
-                                ),
-                                false,
-                            ),
-                            (
-                                printpdf::Point::new(
-                                    Mm(box_.bottom_right.x.into()),
-                                    Mm((resume_layout.height - box_.bottom_right.y).into()),
-                                ),
-                                false,
-                            ),
-                            (
-                                printpdf::Point::new(
-                                    Mm(box_.top_left.x.into()),
-                                    Mm((resume_layout.height - box_.bottom_right.y).into()),
-                                ),
-                                false,
-                            ),
-                        ];
+                        let points = rectangle(box_, 0.0, page_height);
                         let line1 = Line {
                             points,
                             is_closed: true,
@@ -140,28 +509,40 @@ impl PdfLayout {
                         current_layer.add_shape(line1);
                     };
 
-                    current_layer.use_text(
-                        element.item.clone(),
-                        (element.font.size * 2.0) as f64,
-                        Mm(box_.top_left.x.into()),
-                        Mm((resume_layout.height
-                            - (box_.top_left.y + element.font.get_height(&font_dict)))
-                        .into()),
-                        printpdf_font_dict
-                            .get(&element.font.full_name())
-                            .unwrap_or_else(|| {
-                                printpdf_font_dict
-                                    .get(&Font::default().full_name())
-                                    .unwrap()
-                            }),
-                    );
+                    let baseline = box_.top_left.y + element.font().get_baseline(&font_dict);
+
+                    // Glyphs are written one by one at the positions computed by the shaper,
+                    // so kerning, ligatures and complex scripts look the same as they measure.
+                    // The font is switched whenever a glyph comes from one of the fallbacks.
+                    current_layer.set_fill_color(pdf_color(&element.text_color()));
+                    current_layer.begin_text_section();
+                    let mut current_font: Option<String> = None;
+                    for (text, glyphs) in element.shape_clusters(box_.width(), &font_dict) {
+                        begin_actual_text(&current_layer, &text);
+                        for glyph in glyphs {
+                            if current_font.as_ref() != Some(&glyph.font) {
+                                current_layer.set_font(
+                                    &printpdf_font_dict[&glyph.font],
+                                    element.font().size as f64,
+                                );
+                                current_font = Some(glyph.font.clone());
+                            }
+                            current_layer.set_text_matrix(TextMatrix::Translate(
+                                Pt((box_.top_left.x + glyph.x) as f64),
+                                Pt((page_height - (baseline + glyph.y)) as f64),
+                            ));
+                            current_layer.write_codepoints([glyph.glyph_id]);
+                        }
+                        end_actual_text(&current_layer);
+                    }
+                    current_layer.end_text_section();
 
                     if let Some(url) = &element.url {
                         let rect = Rect::new(
-                            Mm(box_.top_left.x.into()),
-                            Mm((resume_layout.height - box_.bottom_right.y).into()),
-                            Mm(box_.bottom_right.x.into()),
-                            Mm((resume_layout.height - box_.top_left.y).into()),
+                            mm(box_.top_left.x),
+                            mm(page_height - box_.bottom_right.y),
+                            mm(box_.bottom_right.x),
+                            mm(page_height - box_.top_left.y),
                         );
                         current_layer.add_link_annotation(LinkAnnotation::new(
                             rect,
@@ -182,41 +563,36 @@ impl PdfLayout {
 
         log::info!("Document is saved to {}", filepath.to_str().unwrap());
 
-        Ok(())
+        Ok(diagnostics)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{data_schema, font::FontDict, layout_schema, resume_data};
+    use crate::resume_data;
 
     use super::*;
 
     #[test]
     fn test_compute_blueprint() {
-        let schema = fs::read_to_string("data/data-schemas.json").unwrap();
-        let data_schemas = data_schema::DataSchema::from_json(&schema);
+        // The sample schemas are rendered from a storage with the bundled fonts installed
+        let dir = std::env::temp_dir().join("cvdl-test-compute-blueprint");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for file in [
+            "data-schemas.json",
+            "layout-schemas.json",
+            "resume-layouts.json",
+            "themes.json",
+        ] {
+            fs::copy(Path::new("data").join(file), dir.join(file)).unwrap();
+        }
+        let local_storage = LocalStorage::custom_dir(dir.to_str().unwrap());
+        local_storage.initiate_local_storage();
 
-        let resume = fs::read_to_string("data/resume2.json").unwrap();
+        let resume = fs::read_to_string("data/resumes/resume2.json").unwrap();
         let resume_data = resume_data::ResumeData::from_json(&resume);
 
-        let schema = fs::read_to_string("data/layout-schemas.json").unwrap();
-        let layout_schemas = layout_schema::LayoutSchema::from_json(&schema);
-
-        let resume_layout = resume_layout::ResumeLayout::from_json(
-            &fs::read_to_string("data/resume-layout.json").unwrap(),
-        );
-
-        let mut font_dict = FontDict::new();
-
-        PdfLayout::render(
-            layout_schemas,
-            resume_data,
-            data_schemas,
-            resume_layout,
-            Path::new("results/output.pdf"),
-            true,
-        )
-        .unwrap();
+        PdfLayout::render(local_storage, resume_data, &dir.join("output.pdf"), true).unwrap();
     }
 }
@@ -1,21 +1,206 @@
 use std::path::Path;
 
-use image::{DynamicImage, Rgba, Pixel};
-use rusttype::{point, Scale};
+use image::{DynamicImage, Pixel, Rgba};
+use rusttype::{point, GlyphId, Scale};
 
 use crate::{
-    any_layout::AnyLayout, data_schema::DataSchema, font::Font, layout_schema::LayoutSchema,
-    local_storage::LocalStorage, resume_data::ResumeData,
+    any_layout::AnyLayout,
+    color::Color,
+    data_schema::DataSchema,
+    diagnostic::Diagnostic,
+    font_registry::FontRegistry,
+    image_element::ImageElement,
+    layout_schema::LayoutSchema,
+    local_storage::LocalStorage,
+    meter::{star_points, MeterStyle},
+    resume_data::ResumeData,
+    shape::{LineStyle, Shape},
+    spatial_box::SpatialBox,
 };
 
 pub struct PngLayout;
 
+type Image = image::ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+// RGBA pixels of every page, row by row
+type Pixels = Vec<Vec<[u8; 4]>>;
+
+fn rgba(color: &Color) -> Rgba<u8> {
+    Rgba([color.r, color.g, color.b, color.a])
+}
+
+// Whether a point is inside a box with rounded corners
+fn inside(x: f32, y: f32, box_: &SpatialBox, radius: f32) -> bool {
+    let radius = radius
+        .min(box_.width() / 2.0)
+        .min(box_.height() / 2.0)
+        .max(0.0);
+    let (left, top) = (box_.top_left.x, box_.top_left.y);
+    let (right, bottom) = (box_.bottom_right.x, box_.bottom_right.y);
+    if x < left || x > right || y < top || y > bottom {
+        return false;
+    }
+    // Distance to the rectangle of the centers of the corners
+    let dx = x - x.clamp(left + radius, right - radius);
+    let dy = y - y.clamp(top + radius, bottom - radius);
+    dx * dx + dy * dy <= radius * radius
+}
+
+// Whether a point is inside a polygon, by the even-odd rule
+fn inside_polygon(x: f32, y: f32, points: &[(f32, f32)]) -> bool {
+    let mut inside = false;
+    for (i, &(x1, y1)) in points.iter().enumerate() {
+        let (x2, y2) = points[(i + 1) % points.len()];
+        if (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Whether a point at `position` along a line is on a dash of the style
+fn on_dash(style: LineStyle, width: f32, position: f32) -> bool {
+    match style.dash_pattern(width) {
+        Some((dash, gap)) => position.rem_euclid(dash + gap) < dash,
+        None => true,
+    }
+}
+
+// Pixels are sampled at their centers
+fn draw_shape(image: &mut Image, box_: &SpatialBox, shape: &Shape) {
+    // Icons are rasterized rather than sampled
+    if let Shape::Icon { name, color } = shape {
+        match name.rasterize(*color, box_, 1.0) {
+            Ok((drawn, pixels)) => composite(image, &drawn, &pixels, |_, _| true),
+            Err(e) => log::warn!("Icon {:?} could not be drawn, {}", name, e),
+        }
+        return;
+    }
+
+    let (width, height) = image.dimensions();
+    let x_range =
+        (box_.top_left.x.max(0.0) as u32)..(box_.bottom_right.x.ceil().max(0.0) as u32).min(width);
+    let y_range =
+        (box_.top_left.y.max(0.0) as u32)..(box_.bottom_right.y.ceil().max(0.0) as u32).min(height);
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let color = match shape {
+                Shape::Rectangle { fill, border } => {
+                    let radius = border.map_or(0.0, |b| b.radius);
+                    let on_border = border.filter(|b| {
+                        let inner = SpatialBox::new(
+                            box_.top_left.move_x_by(b.width).move_y_by(b.width),
+                            box_.bottom_right.move_x_by(-b.width).move_y_by(-b.width),
+                        );
+                        // Dashes run along the horizontal edges and down the vertical ones
+                        let position = if py < inner.top_left.y || py > inner.bottom_right.y {
+                            px - box_.top_left.x
+                        } else {
+                            py - box_.top_left.y
+                        };
+                        inside(px, py, box_, radius)
+                            && !inside(px, py, &inner, radius - b.width)
+                            && on_dash(b.style, b.width, position)
+                    });
+                    match on_border {
+                        Some(b) => Some(b.color),
+                        None => fill.filter(|_| inside(px, py, box_, radius)),
+                    }
+                }
+                Shape::Line {
+                    thickness,
+                    color,
+                    style,
+                    vertical: false,
+                } => {
+                    let middle = (box_.top_left.y + box_.bottom_right.y) / 2.0;
+                    ((py - middle).abs() <= thickness / 2.0
+                        && on_dash(*style, *thickness, px - box_.top_left.x))
+                    .then_some(*color)
+                }
+                Shape::Line {
+                    thickness,
+                    color,
+                    style,
+                    vertical: true,
+                } => {
+                    let middle = (box_.top_left.x + box_.bottom_right.x) / 2.0;
+                    ((px - middle).abs() <= thickness / 2.0
+                        && on_dash(*style, *thickness, py - box_.top_left.y))
+                    .then_some(*color)
+                }
+                Shape::Dot { color } => {
+                    inside(px, py, box_, box_.width().min(box_.height()) / 2.0).then_some(*color)
+                }
+                Shape::Icon { .. } => None,
+                Shape::Meter {
+                    style,
+                    filled,
+                    color,
+                    empty_color,
+                } => {
+                    let on_mark = match style {
+                        MeterStyle::Bar => inside(px, py, box_, box_.height() / 2.0),
+                        MeterStyle::Dots => {
+                            inside(px, py, box_, box_.width().min(box_.height()) / 2.0)
+                        }
+                        MeterStyle::Stars => inside_polygon(px, py, &star_points(box_)),
+                    };
+                    let is_filled = px < box_.top_left.x + filled * box_.width();
+                    on_mark.then_some(if is_filled { *color } else { *empty_color })
+                }
+            };
+            if let Some(color) = color {
+                image.get_pixel_mut(x, y).blend(&rgba(&color));
+            }
+        }
+    }
+}
+
+// Images are composited over what is drawn below them, circular ones are clipped
+fn draw_image(image: &mut Image, dir: &Path, box_: &SpatialBox, element: &ImageElement) {
+    let (drawn, pixels) = match element.fit_into(dir, box_, 1.0) {
+        Ok(fitted) => fitted,
+        Err(e) => {
+            log::warn!("Image could not be drawn, {}", e);
+            return;
+        }
+    };
+
+    let radius = element.clip_radius(box_);
+    composite(image, &drawn, &pixels, |x, y| {
+        radius.is_none_or(|r| inside(x as f32 + 0.5, y as f32 + 0.5, box_, r))
+    });
+}
+
+// Blends pixels over the image at `drawn`, where `visible` allows it
+fn composite(
+    image: &mut Image,
+    drawn: &SpatialBox,
+    pixels: &Image,
+    visible: impl Fn(u32, u32) -> bool,
+) {
+    let (width, height) = image.dimensions();
+    let (left, top) = (
+        drawn.top_left.x.round().max(0.0) as u32,
+        drawn.top_left.y.round().max(0.0) as u32,
+    );
+    for (x, y, pixel) in pixels.enumerate_pixels() {
+        let (x, y) = (left + x, top + y);
+        if x < width && y < height && visible(x, y) {
+            image.get_pixel_mut(x, y).blend(pixel);
+        }
+    }
+}
+
 impl PngLayout {
     pub fn render(
         local_storage: LocalStorage,
         resume_data: ResumeData,
-    ) -> Vec<image::ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        let mut images: Vec<image::ImageBuffer<Rgba<u8>, Vec<u8>>> = Vec::new();
+    ) -> std::io::Result<(Vec<Image>, Vec<Diagnostic>)> {
+        let mut images: Vec<Image> = Vec::new();
         let data_schemas = &resume_data
             .data_schemas()
             .iter()
@@ -29,47 +214,94 @@ impl PngLayout {
             .collect::<Vec<LayoutSchema>>();
 
         let resume_layout = local_storage.load_resume_layout(resume_data.layout.as_str());
+        let theme = local_storage.load_resume_theme(&resume_data)?;
+
+        let font_registry = FontRegistry::new(FontRegistry::search_paths(
+            &local_storage,
+            &resume_layout.font_paths,
+        ));
 
-        let (font_dict, pages) =
-            AnyLayout::render(&layout_schemas, &resume_data, &data_schemas, &resume_layout)
-                .unwrap();
+        let (font_dict, pages, diagnostics) = AnyLayout::render(
+            layout_schemas,
+            &resume_data,
+            data_schemas,
+            &resume_layout,
+            &theme,
+            &font_registry,
+        )?;
 
-        for (index, page) in pages.iter().enumerate() {
-            let width = resume_layout.width as u32;
-            let height = resume_layout.height as u32;
-            let mut image =
-                DynamicImage::new_rgba8(width, height)
-                    .to_rgba8();
+        for page in pages.iter() {
+            // Pages are rasterized at 72 DPI, so one pixel corresponds to one point
+            let width = resume_layout.page_width()? as u32;
+            let height = resume_layout.page_height()? as u32;
+            let mut image = DynamicImage::new_rgba8(width, height).to_rgba8();
 
             for element_box in page {
                 for element in &element_box.elements {
-                    let text = &element.1.item;
-                    let font = &element.1.font;
+                    // Highlights and shapes are blended with what is already drawn below them
+                    if let Some(background) = element.1.background {
+                        let highlight = Shape::Rectangle {
+                            fill: Some(background),
+                            border: None,
+                        };
+                        draw_shape(&mut image, &element.0, &highlight);
+                    }
+                    if let Some(shape) = &element.1.shape {
+                        draw_shape(&mut image, &element.0, shape);
+                    }
+                    if let Some(image_element) = &element.1.image {
+                        draw_image(&mut image, local_storage.dir(), &element.0, image_element);
+                    }
+
+                    let text_color = element.1.text_color();
+                    let shaped = element.1.shape(element.0.width(), &font_dict);
+                    let font = element.1.font();
                     let scale = Scale::uniform(font.size);
-                    let font = &font_dict
-                        .get(&font.full_name())
-                        .unwrap_or_else(|| font_dict.get(&Font::default().full_name()).unwrap())
-                        .rusttype_font;
-                    let v_metrics = font.v_metrics(scale);
-
-                    // layout the glyphs in a line with 20 pixels padding
-                    let glyphs: Vec<_> = font
-                        .layout(text, scale, point(0.0, v_metrics.ascent))
+                    let baseline = font.get_baseline(&font_dict);
+
+                    // position the shaped glyphs on the baseline, each one is drawn
+                    // with the font it was shaped with
+                    let glyphs: Vec<_> = shaped
+                        .iter()
+                        .map(|g| {
+                            font_dict[&g.font]
+                                .rusttype_font
+                                .glyph(GlyphId(g.glyph_id))
+                                .scaled(scale)
+                                .positioned(point(g.x, baseline + g.y))
+                        })
                         .collect();
 
                     for glyph in glyphs {
                         if let Some(bounding_box) = glyph.pixel_bounding_box() {
                             // Draw the glyph into the image per-pixel by using the draw closure
                             glyph.draw(|x, y, v| {
-                                if (((element.0.top_left.x as u32) + x + (bounding_box.min.x.max(0) as u32)) < width)
-                                && (((element.0.top_left.y as u32) + y + (bounding_box.min.y.max(0) as u32)) < height) {
-                                    image.put_pixel(
-                                        // Offset the position by the glyph bounding box
-                                        element.0.top_left.x as u32 + x + bounding_box.min.x.max(0) as u32,
-                                        element.0.top_left.y as u32 + y + bounding_box.min.y.max(0) as u32,
+                                if (((element.0.top_left.x as u32)
+                                    + x
+                                    + (bounding_box.min.x.max(0) as u32))
+                                    < width)
+                                    && (((element.0.top_left.y as u32)
+                                        + y
+                                        + (bounding_box.min.y.max(0) as u32))
+                                        < height)
+                                {
+                                    image
+                                        .get_pixel_mut(
+                                            // Offset the position by the glyph bounding box
+                                            element.0.top_left.x as u32
+                                                + x
+                                                + bounding_box.min.x.max(0) as u32,
+                                            element.0.top_left.y as u32
+                                                + y
+                                                + bounding_box.min.y.max(0) as u32,
+                                        )
                                         // Turn the coverage into an alpha value
-                                        Rgba([0, 0, 0, (v * 255.0) as u8]),
-                                    )
+                                        .blend(&Rgba([
+                                            text_color.r,
+                                            text_color.g,
+                                            text_color.b,
+                                            (v * text_color.a as f32) as u8,
+                                        ]))
                                 }
                             });
                         }
@@ -80,7 +312,7 @@ impl PngLayout {
             images.push(image);
         }
 
-        images
+        Ok((images, diagnostics))
     }
 
     pub fn render_and_save(
@@ -88,28 +320,27 @@ impl PngLayout {
         resume_data: ResumeData,
         _filepath: &Path,
         _debug: bool,
-    ) -> std::io::Result<()> {
-        let pages = PngLayout::render(local_storage, resume_data);
+    ) -> std::io::Result<Vec<Diagnostic>> {
+        let (pages, diagnostics) = PngLayout::render(local_storage, resume_data)?;
 
         for (index, image) in pages.iter().enumerate() {
             image.save(format!("output_{}.png", index)).unwrap();
-            println!("{}", format!("Generated: output_{}.png", index));
+            println!("Generated: output_{}.png", index);
         }
 
-        Ok(())
+        Ok(diagnostics)
     }
 
     pub fn render_and_pixelize(
         local_storage: LocalStorage,
         resume_data: ResumeData,
-    ) -> Vec<Vec<[u8; 4]>> {
-        let pages = PngLayout::render(local_storage, resume_data);
-        let mut pixels: Vec<Vec<[u8; 4]>> = Vec::new();
+    ) -> std::io::Result<(Pixels, Vec<Diagnostic>)> {
+        let (pages, diagnostics) = PngLayout::render(local_storage, resume_data)?;
+        let mut pixels: Pixels = Vec::new();
         for image in pages {
             pixels.push(image.pixels().map(|p| p.to_owned().0).collect());
         }
 
-        pixels
+        Ok((pixels, diagnostics))
     }
-
 }
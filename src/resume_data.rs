@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
+
+use crate::direction::Direction;
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ResumeData {
     pub layout: String,
+    // Theme whose roles the layouts refer to, it can be swapped at render time
+    #[serde(default = "Option::default")]
+    pub theme: Option<String>,
     #[serde_as(deserialize_as = "Vec<_>")]
     pub sections: Vec<ResumeSection>,
 }
@@ -21,6 +26,9 @@ pub struct ResumeSection {
     pub data: HashMap<ItemName, ItemContent>,
     #[serde_as(deserialize_as = "Vec<HashMap<_, _>>")]
     pub items: Vec<HashMap<ItemName, ItemContent>>,
+    // Overrides the direction of the resume layout for this section
+    #[serde(default = "Option::default")]
+    pub direction: Option<Direction>,
 }
 
 pub type ItemName = String;
@@ -30,21 +38,26 @@ pub type ItemName = String;
 pub enum ItemContent {
     None,
     String(String),
+    Number(f32),
     List(Vec<ItemContent>),
     Url { url: String, text: String },
 }
 
-impl ToString for ItemContent {
-    fn to_string(&self) -> String {
+impl fmt::Display for ItemContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ItemContent::None => String::new(),
-            ItemContent::String(s) => s.clone(),
-            ItemContent::List(l) => l
-                .iter()
-                .map(ItemContent::to_string)
-                .collect::<Vec<String>>()
-                .join(", "),
-            ItemContent::Url { url: _, text } => text.clone(),
+            ItemContent::None => Ok(()),
+            ItemContent::String(s) => write!(f, "{}", s),
+            ItemContent::Number(n) => write!(f, "{}", n),
+            ItemContent::List(l) => write!(
+                f,
+                "{}",
+                l.iter()
+                    .map(ItemContent::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ItemContent::Url { url: _, text } => write!(f, "{}", text),
         }
     }
 }
@@ -122,14 +135,14 @@ mod tests {
 
         let resume_data = ResumeData::from_json(json);
         assert_eq!(resume_data.sections.len(), 2);
-        assert_eq!(resume_data.sections[0].name, "Profile");
+        assert_eq!(resume_data.sections[0].section_name, "Profile");
         assert_eq!(resume_data.sections[0].data_schema, "Profile");
         assert_eq!(resume_data.sections[0].items.len(), 1);
         assert_eq!(
             resume_data.sections[0].items[0]["Name"].to_string(),
             "Alperen"
         );
-        assert_eq!(resume_data.sections[1].name, "Education");
+        assert_eq!(resume_data.sections[1].section_name, "Education");
         assert_eq!(resume_data.sections[1].data_schema, "Education");
         assert_eq!(resume_data.sections[1].items.len(), 2);
         assert_eq!(
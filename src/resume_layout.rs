@@ -1,6 +1,10 @@
+use std::io::{Error, ErrorKind};
+
 use serde::{Deserialize, Serialize};
 
-use crate::{layout_schema::Named, margin::Margin};
+use crate::{
+    direction::Direction, layout_schema::Named, margin::Margin, style::Styles, unit::Unit,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub enum ColumnType {
@@ -20,13 +24,63 @@ impl ColumnType {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum PageSize {
+    A4,
+    #[default]
+    Letter,
+    Legal,
+    A5,
+    // Custom dimensions are given in the unit of the resume layout
+    Custom {
+        width: f32,
+        height: f32,
+    },
+}
+
+impl PageSize {
+    /// Returns the (width, height) of the page in points.
+    pub fn dimensions(&self, unit: Unit) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (Unit::Mm.to_pt(210.0), Unit::Mm.to_pt(297.0)),
+            PageSize::Letter => (Unit::In.to_pt(8.5), Unit::In.to_pt(11.0)),
+            PageSize::Legal => (Unit::In.to_pt(8.5), Unit::In.to_pt(14.0)),
+            PageSize::A5 => (Unit::Mm.to_pt(148.0), Unit::Mm.to_pt(210.0)),
+            PageSize::Custom { width, height } => (unit.to_pt(*width), unit.to_pt(*height)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ResumeLayout {
     pub schema_name: String,
     pub column_type: ColumnType,
     pub margin: Margin,
-    pub width: f32,
-    pub height: f32,
+    #[serde(default = "PageSize::default")]
+    pub page_size: PageSize,
+    // Page dimensions of layouts written before page sizes, read as a custom page size
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub width: Option<f32>,
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    pub height: Option<f32>,
+    // Vertical space between two consecutive sections
+    #[serde(default = "f32::default")]
+    pub section_spacing: f32,
+    // Unit of the margins, the section spacing, the column margin and custom page sizes
+    #[serde(default = "Unit::default")]
+    pub unit: Unit,
+    // Base direction of the document, sections can override it
+    #[serde(default = "Direction::default")]
+    pub direction: Direction,
+    // Font families every font falls back to for the glyphs it does not have
+    #[serde(default = "Vec::new")]
+    pub font_fallbacks: Vec<String>,
+    // Directories to search for local fonts, relative to the storage directory
+    #[serde(default = "Vec::new")]
+    pub font_paths: Vec<String>,
+    // Styles every layout schema of the document can refer to
+    #[serde(default = "Styles::new")]
+    pub styles: Styles,
 }
 
 impl Named for ResumeLayout {
@@ -41,3 +95,122 @@ impl ResumeLayout {
         schemas
     }
 }
+
+// All of the accessors below return lengths in points, which is the unit
+// the layout engine and the backends work with.
+impl ResumeLayout {
+    // Layouts that still give a width and a height are laid out on a page of that size
+    pub fn page_size(&self) -> std::io::Result<PageSize> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => Ok(PageSize::Custom { width, height }),
+            (None, None) => Ok(self.page_size),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Resume layout {} gives only one of width and height, use page_size instead",
+                    self.schema_name
+                ),
+            )),
+        }
+    }
+
+    pub fn page_width(&self) -> std::io::Result<f32> {
+        Ok(self.page_size()?.dimensions(self.unit).0)
+    }
+
+    pub fn page_height(&self) -> std::io::Result<f32> {
+        Ok(self.page_size()?.dimensions(self.unit).1)
+    }
+
+    pub fn page_margin(&self) -> Margin {
+        Margin::new(
+            self.unit.to_pt(self.margin.top),
+            self.unit.to_pt(self.margin.bottom),
+            self.unit.to_pt(self.margin.left),
+            self.unit.to_pt(self.margin.right),
+        )
+    }
+
+    pub fn vertical_margin(&self) -> f32 {
+        self.unit.to_pt(self.column_type.vertical_margin())
+    }
+
+    pub fn section_spacing(&self) -> f32 {
+        self.unit.to_pt(self.section_spacing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_size() {
+        let json = r#"[
+            {
+                "schema_name": "A4-Metric",
+                "column_type": "SingleColumn",
+                "page_size": "A4",
+                "unit": "Mm",
+                "margin": { "top": 10, "bottom": 10, "left": 25.4, "right": 25.4 }
+            },
+            {
+                "schema_name": "Custom",
+                "column_type": { "DoubleColumn": { "vertical_margin": 0.5 } },
+                "page_size": { "Custom": { "width": 6, "height": 9 } },
+                "unit": "In",
+                "margin": { "top": 0, "bottom": 0, "left": 0, "right": 0 }
+            },
+            {
+                "schema_name": "Defaults",
+                "column_type": "SingleColumn",
+                "margin": { "top": 0, "bottom": 0, "left": 0, "right": 0 }
+            }
+        ]"#;
+        let layouts = ResumeLayout::from_json(json);
+
+        assert!((layouts[0].page_width().unwrap() - 595.28).abs() < 0.01);
+        assert!((layouts[0].page_height().unwrap() - 841.89).abs() < 0.01);
+        assert_eq!(layouts[0].page_margin().left, 72.0);
+
+        assert_eq!(layouts[1].page_width().unwrap(), 432.0);
+        assert_eq!(layouts[1].page_height().unwrap(), 648.0);
+        assert_eq!(layouts[1].vertical_margin(), 36.0);
+
+        assert_eq!(layouts[2].page_size, PageSize::Letter);
+        assert_eq!(layouts[2].page_width().unwrap(), 612.0);
+        assert_eq!(layouts[2].page_height().unwrap(), 792.0);
+    }
+
+    #[test]
+    fn test_legacy_dimensions() {
+        let json = r#"[
+            {
+                "schema_name": "SingleColumnSchema",
+                "column_type": "SingleColumn",
+                "width": 595,
+                "height": 842,
+                "margin": { "top": 25, "bottom": 25, "left": 15, "right": 15 }
+            }
+        ]"#;
+        let layout = &ResumeLayout::from_json(json)[0];
+
+        assert_eq!(
+            layout.page_size().unwrap(),
+            PageSize::Custom {
+                width: 595.0,
+                height: 842.0
+            }
+        );
+        assert_eq!(layout.page_width().unwrap(), 595.0);
+        assert_eq!(layout.page_height().unwrap(), 842.0);
+
+        // A single dimension is an error of the layout rather than a page size
+        let layout = ResumeLayout {
+            height: None,
+            ..layout.clone()
+        };
+        let error = layout.page_width().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    color::Color, font::Font, icon::IconName, meter::MeterStyle, style::Style, width::Width,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl LineStyle {
+    /// Lengths of the dashes and of the gaps between them for a line of the given width,
+    /// solid lines have none.
+    pub fn dash_pattern(&self, width: f32) -> Option<(f32, f32)> {
+        match self {
+            LineStyle::Solid => None,
+            LineStyle::Dashed => Some((3.0 * width, 2.0 * width)),
+            LineStyle::Dotted => Some((width, width)),
+        }
+    }
+}
+
+/// Border of a container. The elements of the container are laid out inside of it, so
+/// the border adds to the height of the container.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Border {
+    #[serde(default = "Border::default_width")]
+    pub width: f32,
+    #[serde(default = "Color::default")]
+    pub color: Color,
+    #[serde(default = "LineStyle::default")]
+    pub style: LineStyle,
+    // Radius of the rounded corners of the border and of the background
+    #[serde(default = "f32::default")]
+    pub radius: f32,
+}
+
+impl Border {
+    pub fn default_width() -> f32 {
+        1.0
+    }
+}
+
+/// Graphics drawn in the box of an element, behind its text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    // The box of a container, with its background and its border
+    Rectangle {
+        fill: Option<Color>,
+        border: Option<Border>,
+    },
+    // A horizontal line through the middle of the box, or a vertical one
+    Line {
+        thickness: f32,
+        color: Color,
+        style: LineStyle,
+        vertical: bool,
+    },
+    // A filled circle inscribed in the box
+    Dot {
+        color: Color,
+    },
+    // A bundled icon, contained in the box
+    Icon {
+        name: IconName,
+        color: Color,
+    },
+    // A bar, dot or star of a meter, its left `filled` fraction is drawn in `color`
+    Meter {
+        style: MeterStyle,
+        filled: f32,
+        color: Color,
+        empty_color: Color,
+    },
+}
+
+/// A horizontal line across its width, used to divide sections.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rule {
+    #[serde(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uid: Uuid,
+    #[serde(default = "Width::default")]
+    pub width: Width,
+    #[serde(default = "Rule::default_thickness")]
+    pub thickness: f32,
+    // Color of the line, falls back to the color cascading from the containers above it
+    #[serde(default = "Option::default")]
+    pub color: Option<Color>,
+    #[serde(default = "LineStyle::default")]
+    pub style: LineStyle,
+    // Space above and below the line, part of the height of the rule
+    #[serde(default = "f32::default")]
+    pub spacing: f32,
+}
+
+impl Rule {
+    pub fn default_thickness() -> f32 {
+        1.0
+    }
+
+    pub fn height(&self) -> f32 {
+        self.thickness + 2.0 * self.spacing
+    }
+
+    pub fn color(&self) -> Color {
+        self.color.unwrap_or_default()
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape::Line {
+            thickness: self.thickness,
+            color: self.color(),
+            style: self.style,
+            vertical: false,
+        }
+    }
+
+    pub fn with_width(&self, width: Width) -> Rule {
+        Rule {
+            uid: self.uid,
+            width,
+            thickness: self.thickness,
+            color: self.color,
+            style: self.style,
+            spacing: self.spacing,
+        }
+    }
+
+    pub fn with_style(&self, style: &Style) -> Rule {
+        Rule {
+            uid: self.uid,
+            width: self.width,
+            thickness: self.thickness,
+            color: self.color.or(style.color),
+            style: self.style,
+            spacing: self.spacing,
+        }
+    }
+
+    pub fn bound_width(&self, width: f32) -> Rule {
+        self.with_width(Width::Absolute(
+            self.width.bound(width).get_fixed_unchecked(),
+        ))
+    }
+
+    pub fn scale_width(&self, w: f32) -> Rule {
+        // Rules have no font, so ems are relative to the default font size
+        self.with_width(self.width.scale(w, Font::default_size()))
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{direction::Direction, font::LoadedFont};
+
+/// A glyph positioned relative to the origin of the text it was shaped from, in points.
+/// Like the rest of the layout engine, `y` grows downwards from the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionedGlyph {
+    // Name of the font the glyph comes from in the font dictionary, which is not the
+    // requested font when the glyph had to be taken from one of its fallbacks
+    pub font: String,
+    pub glyph_id: u16,
+    // Byte index of the first character of the text that produced this glyph
+    pub cluster: usize,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShapedText {
+    pub glyphs: Vec<PositionedGlyph>,
+    // Total advance of the shaped text
+    pub width: f32,
+}
+
+impl ShapedText {
+    /// Shapes `text` with the OpenType tables of `font`, which applies kerning, ligatures
+    /// and the substitutions complex scripts rely on. The glyphs are returned in visual order.
+    /// Without a `direction`, it is guessed from the script of the text.
+    pub fn shape(
+        text: &str,
+        font_name: &str,
+        font: &LoadedFont,
+        size: f32,
+        direction: Option<Direction>,
+        features: &[rustybuzz::Feature],
+    ) -> ShapedText {
+        let Some(face) = font.face() else {
+            log::error!("Font data cannot be parsed for shaping, text will not be rendered");
+            return ShapedText::default();
+        };
+
+        let scale = size / face.units_per_em() as f32;
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        match direction {
+            Some(Direction::Ltr) => buffer.set_direction(rustybuzz::Direction::LeftToRight),
+            Some(Direction::Rtl) => buffer.set_direction(rustybuzz::Direction::RightToLeft),
+            None => {}
+        }
+
+        let output = rustybuzz::shape(&face, features, buffer);
+
+        let mut x = 0.0;
+        let glyphs = output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| {
+                let glyph = PositionedGlyph {
+                    font: font_name.to_string(),
+                    glyph_id: info.glyph_id as u16,
+                    cluster: info.cluster as usize,
+                    x: x + position.x_offset as f32 * scale,
+                    y: -position.y_offset as f32 * scale,
+                };
+                x += position.x_advance as f32 * scale;
+                glyph
+            })
+            .collect();
+
+        ShapedText { glyphs, width: x }
+    }
+
+    // Appends `other` after this text on the same baseline
+    pub fn concat(mut self, other: ShapedText) -> ShapedText {
+        let other = other.move_x_by(self.width);
+        self.glyphs.extend(other.glyphs);
+        ShapedText {
+            glyphs: self.glyphs,
+            width: other.width,
+        }
+    }
+
+    pub fn move_x_by(self, x: f32) -> ShapedText {
+        ShapedText {
+            glyphs: self
+                .glyphs
+                .into_iter()
+                .map(|g| PositionedGlyph { x: g.x + x, ..g })
+                .collect(),
+            width: self.width + x,
+        }
+    }
+
+    pub fn move_clusters_by(self, offset: usize) -> ShapedText {
+        ShapedText {
+            glyphs: self
+                .glyphs
+                .into_iter()
+                .map(|g| PositionedGlyph {
+                    cluster: g.cluster + offset,
+                    ..g
+                })
+                .collect(),
+            width: self.width,
+        }
+    }
+
+    // Adds `spacing` after every cluster, the glyphs of a ligature or of a letter and its
+    // marks stay together
+    pub fn with_letter_spacing(self, spacing: f32) -> ShapedText {
+        let mut clusters = 0;
+        let mut previous = None;
+        let glyphs = self
+            .glyphs
+            .into_iter()
+            .map(|g| {
+                if previous != Some(g.cluster) {
+                    clusters += 1;
+                    previous = Some(g.cluster);
+                }
+                PositionedGlyph {
+                    x: g.x + spacing * (clusters - 1) as f32,
+                    ..g
+                }
+            })
+            .collect();
+        ShapedText {
+            glyphs,
+            width: self.width + spacing * clusters as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        font::{Font, FontDict, FontLoadSource, FontLoader, FontSource},
+        font_registry::FontRegistry,
+    };
+
+    #[test]
+    fn test_kerning() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+        let font = Font::default();
+
+        let shaped = font.shape("AV", &font_dict);
+        assert_eq!(shaped.glyphs.len(), 2);
+        assert!(shaped.width < font.get_width("A", &font_dict) + font.get_width("V", &font_dict));
+        assert_eq!(shaped.glyphs[1].cluster, 1);
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_from_path(
+            Font::default().full_name(),
+            "assets/Exo/static/Exo-Medium.ttf".to_string(),
+        );
+        let font = Font::default()
+            .with_fallbacks(vec!["Missing".to_string()])
+            .inherit_fallbacks(&["Exo".to_string()]);
+        assert_eq!(
+            font.font_chain()
+                .iter()
+                .map(|f| f.full_name())
+                .collect::<Vec<String>>(),
+            vec!["Arial-Medium", "Missing-Medium", "Exo-Medium"]
+        );
+
+        // Fallbacks that could not be loaded are skipped, glyphs that no font has
+        // are taken from the font itself
+        let shaped = font.shape("Aא", &font_dict);
+        assert_eq!(shaped.glyphs.len(), 2);
+        assert!(shaped
+            .glyphs
+            .iter()
+            .all(|g| g.font == Font::default().full_name()));
+        assert!(!font_dict[&Font::default().full_name()].has_glyph('א'));
+
+        // Fallbacks found in the font search paths are loaded from there, and the
+        // glyphs the font does not have are taken from them
+        let font_registry = FontRegistry::new(vec![
+            PathBuf::from("assets"),
+            PathBuf::from("/usr/share/fonts/truetype/dejavu"),
+        ]);
+        let font = Font {
+            name: "Exo".to_string(),
+            source: FontSource::Local,
+            ..Font::default()
+        }
+        .with_fallbacks(vec!["DejaVu Sans".to_string()]);
+        let mut font_dict = FontDict::new();
+        font_dict.load_font_chain(&font, &font_registry);
+        let fallback = &font_dict["DejaVu Sans-Medium"];
+        assert!(matches!(fallback.source, FontLoadSource::Local(_)));
+
+        let shaped = font.shape("Aא", &font_dict);
+        assert_eq!(shaped.glyphs.len(), 2);
+        assert_eq!(shaped.glyphs[0].font, "Exo-Medium");
+        assert_eq!(shaped.glyphs[1].font, "DejaVu Sans-Medium");
+        assert_ne!(shaped.glyphs[1].glyph_id, 0);
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{alignment::Alignment, color::Color, font::PartialFont, margin::Margin};
+
+/// Properties declared once under a name and referenced by elements and containers with
+/// `style`. The font and the color of the style of a container cascade to its elements,
+/// and properties set on a node itself win over the ones of its style, fonts are merged
+/// property by property.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Style {
+    #[serde(default = "PartialFont::default")]
+    pub font: PartialFont,
+    #[serde(default = "Option::default")]
+    pub color: Option<Color>,
+    #[serde(default = "Option::default")]
+    pub margin: Option<Margin>,
+    #[serde(default = "Option::default")]
+    pub alignment: Option<Alignment>,
+}
+
+pub type Styles = HashMap<String, Style>;
+
+impl Style {
+    // The style named `name`, unknown styles are reported and leave the node as it is
+    pub fn find(name: &Option<String>, styles: &Styles) -> Style {
+        let Some(name) = name else {
+            return Style::default();
+        };
+        styles.get(name).cloned().unwrap_or_else(|| {
+            log::warn!("Style {} is not defined", name);
+            Style::default()
+        })
+    }
+
+    // This style with the font and the color falling back to the ones cascading from `parent`
+    pub fn over(&self, parent: &Style) -> Style {
+        Style {
+            font: self.font.over(&parent.font),
+            color: self.color.or(parent.color),
+            margin: self.margin,
+            alignment: self.alignment,
+        }
+    }
+
+    // What cascades to the elements of a container, margins and alignments stay with it
+    pub fn inherited(&self) -> Style {
+        Style {
+            font: self.font.clone(),
+            color: self.color,
+            margin: None,
+            alignment: None,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use rustybuzz::{ttf_parser::Tag, Feature};
+use serde::{Deserialize, Serialize};
+
+use crate::font::{Font, FontDict};
+
+/// Case of the text of an element, applied before it is measured.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum TextTransform {
+    #[default]
+    None,
+    Upper,
+    Lower,
+    // The first letter of every word is capitalized
+    Title,
+    // Lowercase letters are drawn with the small capitals of the font, fonts without
+    // them get uppercase letters instead
+    SmallCaps,
+}
+
+impl TextTransform {
+    const SMALL_CAPS: Tag = Tag::from_bytes(b"smcp");
+
+    pub fn apply(&self, text: &str, font: &Font, font_dict: &FontDict) -> String {
+        match self {
+            TextTransform::None => text.to_string(),
+            TextTransform::Upper => text.to_uppercase(),
+            TextTransform::Lower => text.to_lowercase(),
+            TextTransform::Title => {
+                let mut after_letter = false;
+                text.chars()
+                    .flat_map(|c| {
+                        let starts_word = !after_letter;
+                        after_letter = c.is_alphanumeric() || c == '\'' || c == '’';
+                        if starts_word {
+                            c.to_uppercase().collect::<Vec<char>>()
+                        } else {
+                            vec![c]
+                        }
+                    })
+                    .collect()
+            }
+            TextTransform::SmallCaps => {
+                if font
+                    .loaded_font(font_dict)
+                    .has_feature(TextTransform::SMALL_CAPS)
+                {
+                    text.to_string()
+                } else {
+                    text.to_uppercase()
+                }
+            }
+        }
+    }
+
+    // OpenType features the text is shaped with
+    pub fn features(&self) -> Vec<Feature> {
+        match self {
+            TextTransform::SmallCaps => vec![Feature::new(TextTransform::SMALL_CAPS, 1, ..)],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let font_dict = FontDict::new();
+        let font = Font::default();
+        let text = "senior engineer, o'brien & co.";
+        assert_eq!(
+            TextTransform::Upper.apply(text, &font, &font_dict),
+            "SENIOR ENGINEER, O'BRIEN & CO."
+        );
+        assert_eq!(
+            TextTransform::Title.apply(text, &font, &font_dict),
+            "Senior Engineer, O'brien & Co."
+        );
+        assert_eq!(
+            TextTransform::Lower.apply("Rust & C++", &font, &font_dict),
+            "rust & c++"
+        );
+        assert_eq!(TextTransform::None.apply(text, &font, &font_dict), text);
+    }
+}
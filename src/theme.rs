@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{layout_schema::Named, style::Styles};
+
+/// Fonts and colors of semantic roles such as heading, subheading, body, muted or accent.
+/// Layouts refer to the roles with `style`, so the same resume and layouts can be rendered
+/// in another theme by choosing it at render time. Styles of the resume layout and of the
+/// layout schemas win over the roles of the same name.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Theme {
+    pub schema_name: String,
+    #[serde(default = "Styles::new")]
+    pub roles: Styles,
+}
+
+impl Named for Theme {
+    fn name(&self) -> &str {
+        &self.schema_name
+    }
+}
+
+impl Theme {
+    pub fn from_json(json: &str) -> Vec<Theme> {
+        let themes: Vec<Theme> = serde_json::from_str(json).unwrap();
+        themes
+    }
+
+    // The roles of the theme under the styles of the document
+    pub fn styles_under(&self, styles: &Styles) -> Styles {
+        let mut roles = self.roles.clone();
+        roles.extend(styles.clone());
+        roles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{layout::SectionLayout, layout_schema::LayoutSchema, local_storage::LocalStorage};
+
+    #[test]
+    fn test_roles() {
+        let themes = Theme::from_json(
+            r##"[
+                {
+                    "schema_name": "classic",
+                    "roles": {
+                        "heading": { "font": { "name": "Exo", "size": 14.0, "weight": "Bold" } },
+                        "muted": { "color": "#666666" }
+                    }
+                },
+                {
+                    "schema_name": "modern",
+                    "roles": {
+                        "heading": {
+                            "font": { "name": "Exo", "size": 16.0, "weight": "Light" },
+                            "color": "#1F6FEB"
+                        },
+                        "muted": { "color": "#8B949E" }
+                    }
+                }
+            ]"##,
+        );
+        let schema = &LayoutSchema::from_json(
+            r#"[{
+                "schema_name": "Work",
+                "header_layout_schema": { "Text": { "item": "Work", "style": "heading" } },
+                "item_layout_schema": { "Ref": { "item": "Date", "style": "muted" } }
+            }]"#,
+        )[0];
+
+        let header = |theme: &Theme| match schema
+            .resolve_styles(&theme.styles_under(&Styles::new()))
+            .header_layout_schema
+        {
+            SectionLayout::Text(e) => e,
+            _ => panic!("the header is not a text"),
+        };
+        let classic = header(&themes[0]);
+        let modern = header(&themes[1]);
+        assert_eq!(classic.font().size, 14.0);
+        assert_eq!(classic.color, None);
+        assert_eq!(modern.font().size, 16.0);
+        assert_eq!(modern.color.unwrap().b, 0xEB);
+
+        // The styles of the document win over the roles of the theme
+        let mut styles = Styles::new();
+        styles.insert("heading".to_string(), Default::default());
+        let header = schema
+            .resolve_styles(&themes[1].styles_under(&styles))
+            .header_layout_schema;
+        let SectionLayout::Text(header) = header else {
+            panic!("the header is not a text");
+        };
+        assert_eq!(header.font().size, 12.0);
+    }
+
+    #[test]
+    fn test_bundled_themes() {
+        let themes = Theme::from_json(include_str!("../data/themes.json"));
+        let schema = &LayoutSchema::from_json(include_str!("../data/layout-schemas.json"))[0];
+
+        // The bundled layouts only refer to roles, so every theme draws them differently
+        let header = |theme: &Theme| match schema
+            .resolve_styles(&theme.styles_under(&Styles::new()))
+            .header_layout_schema
+        {
+            SectionLayout::Ref(e) => e,
+            _ => panic!("the header is not a ref"),
+        };
+        let classic = header(&themes[0]);
+        let modern = header(&themes[1]);
+        assert_eq!(classic.font().name, "Exo");
+        assert_eq!(classic.font().size, 14.0);
+        assert_eq!(modern.font().size, 16.0);
+        assert_eq!(modern.color.unwrap().b, 0xEB);
+    }
+
+    #[test]
+    fn test_load_theme() {
+        let dir = std::env::temp_dir().join("cvdl-test-load-theme");
+        let _ = std::fs::remove_dir_all(&dir);
+        let local_storage = LocalStorage::custom_dir(dir.to_str().unwrap());
+        local_storage.initiate_local_storage();
+
+        // The bundled themes are installed into the storage
+        assert_eq!(local_storage.list_themes(), vec!["classic", "modern"]);
+        assert!(local_storage.load_theme("modern").is_ok());
+
+        let error = local_storage.load_theme("retro").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("[classic, modern]"));
+    }
+}
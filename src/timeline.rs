@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    color::Color,
+    font::Font,
+    layout::SectionLayout,
+    shape::{LineStyle, Rule, Shape},
+    width::Width,
+};
+
+/// An entry of a timeline, its elements are stacked next to a vertical spine with a marker
+/// and its date is laid out in a gutter on the other side of the spine. Used as the item
+/// layout of a section, every item draws its own part of the spine, so the spine runs
+/// through the whole section. Items that do not fit are split at the page break between
+/// their lines, their spine runs to the bottom margin and continues from the top margin
+/// of the next page.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Timeline {
+    #[serde(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uid: Uuid,
+    // Laid out in the gutter, its first line shares a baseline with the first element
+    pub date: Box<SectionLayout>,
+    pub elements: Vec<SectionLayout>,
+    #[serde(default = "Width::default")]
+    pub width: Width,
+    #[serde(default = "Timeline::default_gutter")]
+    pub gutter: f32,
+    // Space on both sides of the spine
+    #[serde(default = "Timeline::default_gap")]
+    pub gap: f32,
+    #[serde(default = "Rule::default_thickness")]
+    pub thickness: f32,
+    #[serde(default = "Color::default")]
+    pub color: Color,
+    #[serde(default = "LineStyle::default")]
+    pub style: LineStyle,
+    // Diameter of the marker on the spine
+    #[serde(default = "Timeline::default_marker")]
+    pub marker: f32,
+}
+
+impl Timeline {
+    pub fn default_gutter() -> f32 {
+        72.0
+    }
+
+    pub fn default_gap() -> f32 {
+        8.0
+    }
+
+    pub fn default_marker() -> f32 {
+        6.0
+    }
+
+    /// Applies `f` to the date and to the elements.
+    pub fn map(&self, mut f: impl FnMut(&SectionLayout) -> SectionLayout) -> Timeline {
+        Timeline {
+            uid: self.uid,
+            date: Box::new(f(&self.date)),
+            elements: self.elements.iter().map(f).collect(),
+            width: self.width,
+            gutter: self.gutter,
+            gap: self.gap,
+            thickness: self.thickness,
+            color: self.color,
+            style: self.style,
+            marker: self.marker,
+        }
+    }
+
+    pub fn with_width(&self, width: Width) -> Timeline {
+        Timeline {
+            uid: self.uid,
+            date: self.date.clone(),
+            elements: self.elements.clone(),
+            width,
+            gutter: self.gutter,
+            gap: self.gap,
+            thickness: self.thickness,
+            color: self.color,
+            style: self.style,
+            marker: self.marker,
+        }
+    }
+
+    // Horizontal center of the spine
+    pub fn spine_x(&self) -> f32 {
+        self.gutter + self.gap + self.marker.max(self.thickness) / 2.0
+    }
+
+    // Distance of the elements from the left of the timeline
+    pub fn body_offset(&self) -> f32 {
+        self.gutter + 2.0 * self.gap + self.marker.max(self.thickness)
+    }
+
+    pub fn fonts(&self) -> Vec<Font> {
+        let mut fonts = self.date.fonts();
+        fonts.extend(self.elements.iter().flat_map(|e| e.fonts()));
+        fonts
+    }
+
+    pub fn is_instantiated(&self) -> bool {
+        self.date.is_instantiated() && self.elements.iter().all(|e| e.is_instantiated())
+    }
+
+    pub fn bound_width(&self, width: f32) -> Timeline {
+        let bound = self.width.bound(width).get_fixed_unchecked();
+        let body = f32::max(bound - self.body_offset(), 0.0);
+
+        Timeline {
+            uid: self.uid,
+            date: Box::new(self.date.bound_width(self.gutter)),
+            elements: self.elements.iter().map(|e| e.bound_width(body)).collect(),
+            width: Width::Absolute(bound),
+            gutter: self.gutter,
+            gap: self.gap,
+            thickness: self.thickness,
+            color: self.color,
+            style: self.style,
+            marker: self.marker,
+        }
+    }
+
+    pub fn scale_width(&self, w: f32) -> Timeline {
+        // Timelines have no font of their own, so ems are relative to the default font size
+        self.map(|e| e.scale_width(w))
+            .with_width(self.width.scale(w, Font::default_size()))
+    }
+
+    pub fn spine(&self) -> Shape {
+        Shape::Line {
+            thickness: self.thickness,
+            color: self.color,
+            style: self.style,
+            vertical: true,
+        }
+    }
+
+    pub fn marker(&self) -> Shape {
+        Shape::Dot { color: self.color }
+    }
+}
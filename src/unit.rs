@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Physical units a document can be written in. Internally, all lengths are
+/// kept in points (1/72 inch), which is also the unit font sizes are given in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Unit {
+    #[default]
+    Pt,
+    Mm,
+    In,
+}
+
+impl Unit {
+    pub const POINTS_PER_INCH: f32 = 72.0;
+    pub const MM_PER_INCH: f32 = 25.4;
+
+    pub fn to_pt(self, value: f32) -> f32 {
+        match self {
+            Unit::Pt => value,
+            Unit::Mm => value / Unit::MM_PER_INCH * Unit::POINTS_PER_INCH,
+            Unit::In => value * Unit::POINTS_PER_INCH,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use ttf_parser::{Face, GlyphId, OutlineBuilder, Tag};
+
+// Tables that only make sense for the variable font, or that describe hinting and device
+// metrics of the original outlines. They are left out of the instances.
+const DROPPED_TABLES: [&[u8; 4]; 14] = [
+    b"fvar", b"gvar", b"avar", b"cvar", b"HVAR", b"VVAR", b"MVAR", b"STAT", b"fpgm", b"prep",
+    b"cvt ", b"hdmx", b"LTSH", b"VDMX",
+];
+
+// Tables that are rebuilt for the instances
+const REBUILT_TABLES: [&[u8; 4]; 7] = [
+    b"glyf", b"loca", b"hmtx", b"head", b"hhea", b"maxp", b"OS/2",
+];
+
+/// Creates a static instance of a variable TrueType font at the given `weight` and
+/// `width`, so that the backends that do not understand font variations, rusttype and
+/// PDF viewers, draw the same outlines the shaper measures. Returns `None` for fonts
+/// that are not variable or are not TrueType flavored.
+pub fn instance(data: &[u8], weight: f32, width: f32) -> Option<Vec<u8>> {
+    let mut face = Face::parse(data, 0).ok()?;
+    if !face.is_variable() || face.tables().glyf.is_none() {
+        return None;
+    }
+
+    // Axes the font does not have are simply ignored
+    face.set_variation(Tag::from_bytes(b"wght"), weight);
+    face.set_variation(Tag::from_bytes(b"wdth"), width);
+
+    let mut glyf: Vec<u8> = Vec::new();
+    let mut loca: Vec<u8> = Vec::new();
+    let mut hmtx: Vec<u8> = Vec::new();
+    let mut bbox = (i16::MAX, i16::MAX, i16::MIN, i16::MIN);
+    let mut max_points = 0;
+    let mut max_contours = 0;
+    let mut max_advance = 0;
+
+    for id in 0..face.number_of_glyphs() {
+        let mut contours = Contours::default();
+        face.outline_glyph(GlyphId(id), &mut contours);
+        let glyph = SimpleGlyph::new(contours.finish());
+
+        let advance = face.glyph_hor_advance(GlyphId(id)).unwrap_or(0);
+        max_advance = max_advance.max(advance);
+        hmtx.extend(advance.to_be_bytes());
+        hmtx.extend(glyph.bbox.0.to_be_bytes());
+
+        if !glyph.contours.is_empty() {
+            bbox = (
+                bbox.0.min(glyph.bbox.0),
+                bbox.1.min(glyph.bbox.1),
+                bbox.2.max(glyph.bbox.2),
+                bbox.3.max(glyph.bbox.3),
+            );
+            max_points = max_points.max(glyph.contours.iter().map(Vec::len).sum::<usize>());
+            max_contours = max_contours.max(glyph.contours.len());
+        }
+
+        loca.extend((glyf.len() as u32).to_be_bytes());
+        glyf.extend(glyph.encode());
+    }
+    loca.extend((glyf.len() as u32).to_be_bytes());
+
+    let raw_face = face.raw_face();
+    let table = |tag: &[u8; 4]| raw_face.table(Tag::from_bytes(tag)).map(<[u8]>::to_vec);
+
+    // Bounding box of all glyphs, and long offsets in `loca`
+    let mut head = table(b"head")?;
+    head[8..12].copy_from_slice(&[0; 4]);
+    head[36..38].copy_from_slice(&bbox.0.to_be_bytes());
+    head[38..40].copy_from_slice(&bbox.1.to_be_bytes());
+    head[40..42].copy_from_slice(&bbox.2.to_be_bytes());
+    head[42..44].copy_from_slice(&bbox.3.to_be_bytes());
+    head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+    // Every glyph has its own advance in `hmtx`
+    let mut hhea = table(b"hhea")?;
+    hhea[10..12].copy_from_slice(&max_advance.to_be_bytes());
+    hhea[34..36].copy_from_slice(&face.number_of_glyphs().to_be_bytes());
+
+    // Composite glyphs are flattened into simple ones
+    let mut maxp = table(b"maxp")?;
+    if maxp.len() >= 32 {
+        maxp[6..8].copy_from_slice(&(max_points as u16).to_be_bytes());
+        maxp[8..10].copy_from_slice(&(max_contours as u16).to_be_bytes());
+        maxp[10..14].copy_from_slice(&[0; 4]);
+        maxp[28..32].copy_from_slice(&[0; 4]);
+    }
+
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = vec![
+        (*b"glyf", glyf),
+        (*b"loca", loca),
+        (*b"hmtx", hmtx),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"maxp", maxp),
+    ];
+
+    if let Some(mut os2) = table(b"OS/2") {
+        os2[4..6].copy_from_slice(&(weight.round() as u16).to_be_bytes());
+        tables.push((*b"OS/2", os2));
+    }
+
+    for record in raw_face.table_records {
+        let tag = record.tag.to_bytes();
+        if !DROPPED_TABLES.contains(&&tag) && !REBUILT_TABLES.contains(&&tag) {
+            tables.push((tag, table(&tag)?));
+        }
+    }
+
+    Some(write_font(tables))
+}
+
+// Collects the contours of a TrueType outline as on and off curve points
+#[derive(Default)]
+struct Contours {
+    contours: Vec<Vec<(f32, f32, bool)>>,
+    current: Vec<(f32, f32, bool)>,
+}
+
+impl OutlineBuilder for Contours {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current.push((x, y, true));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.current.push((x, y, true));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.current.push((x1, y1, false));
+        self.current.push((x, y, true));
+    }
+
+    // TrueType outlines have no cubic curves, this is only a close approximation
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0, _) = self.current.last().copied().unwrap_or((x, y, true));
+        self.current.push((
+            (3.0 * (x1 + x2) - x0 - x) / 4.0,
+            (3.0 * (y1 + y2) - y0 - y) / 4.0,
+            false,
+        ));
+        self.current.push((x, y, true));
+    }
+
+    fn close(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+
+        // The outline goes back to where it started, the point is not repeated in `glyf`
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+
+        self.contours.push(contour);
+    }
+}
+
+impl Contours {
+    // Rounds the points to font units, leaving out the on curve points that are implied
+    // by being halfway between two off curve points
+    fn finish(self) -> Vec<Vec<(i16, i16, bool)>> {
+        self.contours
+            .into_iter()
+            .map(|contour| {
+                let n = contour.len();
+                (0..n)
+                    .filter(|&i| {
+                        let (x, y, on_curve) = contour[i];
+                        let (px, py, prev_on) = contour[(i + n - 1) % n];
+                        let (nx, ny, next_on) = contour[(i + 1) % n];
+                        !(on_curve
+                            && !prev_on
+                            && !next_on
+                            && ((px + nx) / 2.0 - x).abs() < 0.01
+                            && ((py + ny) / 2.0 - y).abs() < 0.01)
+                    })
+                    .map(|i| {
+                        let (x, y, on_curve) = contour[i];
+                        (x.round() as i16, y.round() as i16, on_curve)
+                    })
+                    .collect()
+            })
+            .filter(|contour: &Vec<(i16, i16, bool)>| !contour.is_empty())
+            .collect()
+    }
+}
+
+struct SimpleGlyph {
+    contours: Vec<Vec<(i16, i16, bool)>>,
+    bbox: (i16, i16, i16, i16),
+}
+
+impl SimpleGlyph {
+    fn new(contours: Vec<Vec<(i16, i16, bool)>>) -> SimpleGlyph {
+        let points = contours.iter().flatten();
+        let bbox = points.fold(
+            (i16::MAX, i16::MAX, i16::MIN, i16::MIN),
+            |(x_min, y_min, x_max, y_max), &(x, y, _)| {
+                (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y))
+            },
+        );
+
+        if contours.is_empty() {
+            SimpleGlyph {
+                contours,
+                bbox: (0, 0, 0, 0),
+            }
+        } else {
+            SimpleGlyph { contours, bbox }
+        }
+    }
+
+    // Empty glyphs take no space, the others are written with 16 bit coordinate deltas
+    // and padded to 4 bytes
+    fn encode(&self) -> Vec<u8> {
+        if self.contours.is_empty() {
+            return Vec::new();
+        }
+
+        let mut data: Vec<u8> = Vec::new();
+        data.extend((self.contours.len() as i16).to_be_bytes());
+        for value in [self.bbox.0, self.bbox.1, self.bbox.2, self.bbox.3] {
+            data.extend(value.to_be_bytes());
+        }
+
+        let mut end = 0;
+        for contour in &self.contours {
+            end += contour.len();
+            data.extend(((end - 1) as u16).to_be_bytes());
+        }
+
+        // No instructions
+        data.extend(0u16.to_be_bytes());
+
+        let points = self.contours.iter().flatten().collect::<Vec<_>>();
+        data.extend(points.iter().map(|&&(_, _, on_curve)| on_curve as u8));
+
+        let mut previous = 0;
+        for &&(x, _, _) in &points {
+            data.extend((x.wrapping_sub(previous)).to_be_bytes());
+            previous = x;
+        }
+        let mut previous = 0;
+        for &&(_, y, _) in &points {
+            data.extend((y.wrapping_sub(previous)).to_be_bytes());
+            previous = y;
+        }
+
+        data.resize(data.len().next_multiple_of(4), 0);
+        data
+    }
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+// Writes an sfnt file with the given tables, sorted by their tag as the format requires
+pub(crate) fn write_font(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut font: Vec<u8> = Vec::new();
+    font.extend(0x00010000u32.to_be_bytes());
+    font.extend(num_tables.to_be_bytes());
+    font.extend(search_range.to_be_bytes());
+    font.extend(entry_selector.to_be_bytes());
+    font.extend((num_tables * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_offset = 0;
+    for (tag, data) in &tables {
+        if tag == b"head" {
+            head_offset = offset;
+        }
+        font.extend(tag);
+        font.extend(checksum(data).to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+
+    for (_, data) in &tables {
+        font.extend(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&font));
+    font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+
+    font
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance() {
+        let variable = std::fs::read("assets/Exo/Exo-VariableFont_wght.ttf").unwrap();
+        let bold_data = std::fs::read("assets/Exo/static/Exo-Bold.ttf").unwrap();
+
+        // Static fonts are left as they are
+        assert!(instance(&bold_data, 700.0, 100.0).is_none());
+
+        let instance = instance(&variable, 700.0, 100.0).unwrap();
+        let face = Face::parse(&instance, 0).unwrap();
+        let bold = Face::parse(&bold_data, 0).unwrap();
+        assert!(!face.is_variable());
+        assert_eq!(face.weight().to_number(), 700);
+
+        // Outlines and advances match the static bold cut of the family
+        let glyph = face.glyph_index('W').unwrap();
+        let bold_glyph = bold.glyph_index('W').unwrap();
+        let advance = face.glyph_hor_advance(glyph).unwrap() as i32;
+        let bold_advance = bold.glyph_hor_advance(bold_glyph).unwrap() as i32;
+        assert!((advance - bold_advance).abs() <= 1);
+        let bbox = face.glyph_bounding_box(glyph).unwrap();
+        let bold_bbox = bold.glyph_bounding_box(bold_glyph).unwrap();
+        assert!((bbox.width() - bold_bbox.width()).abs() <= 2);
+
+        assert!(rusttype::Font::try_from_vec(instance).is_some());
+    }
+}
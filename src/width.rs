@@ -1,11 +1,158 @@
+use std::str::FromStr;
+
 use serde::{de::Visitor, Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Default)]
+use crate::unit::Unit;
+
+/// A single width value, used as the base and the bounds of `Width::Bounded`.
+/// Absolute values are always kept in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Absolute(f32),
+    Percentage(f32),
+    Em(f32),
+    Fill,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum Width {
     Absolute(f32),
     Percentage(f32),
+    Em(f32),
     #[default]
     Fill,
+    // A width clamped between `min` and `max`, written as "fill(min=40mm, max=50%)"
+    Bounded {
+        width: Length,
+        min: Option<Length>,
+        max: Option<Length>,
+    },
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Length, Self::Err> {
+        let s = s.trim();
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid width: {}", s))
+        };
+
+        if s == "fill" {
+            Ok(Length::Fill)
+        } else if let Some(stripped) = s.strip_suffix('%') {
+            Ok(Length::Percentage(parse(stripped)?))
+        } else if let Some(stripped) = s.strip_suffix("em") {
+            Ok(Length::Em(parse(stripped)?))
+        } else if let Some(stripped) = s.strip_suffix("px").or_else(|| s.strip_suffix("pt")) {
+            // Pixels and points are the same thing at the 72 DPI the layout engine uses
+            Ok(Length::Absolute(parse(stripped)?))
+        } else if let Some(stripped) = s.strip_suffix("mm") {
+            Ok(Length::Absolute(Unit::Mm.to_pt(parse(stripped)?)))
+        } else if let Some(stripped) = s.strip_suffix("in") {
+            Ok(Length::Absolute(Unit::In.to_pt(parse(stripped)?)))
+        } else {
+            Err(format!("invalid width: {}", s))
+        }
+    }
+}
+
+impl std::fmt::Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Length::Absolute(w) => write!(f, "{}pt", w),
+            Length::Percentage(w) => write!(f, "{}%", w),
+            Length::Em(w) => write!(f, "{}em", w),
+            Length::Fill => write!(f, "fill"),
+        }
+    }
+}
+
+impl Length {
+    fn scale(&self, total_width: f32, font_size: f32) -> Length {
+        match self {
+            Length::Percentage(w) => Length::Absolute(*w / 100.0 * total_width),
+            Length::Em(w) => Length::Absolute(*w * font_size),
+            Length::Absolute(_) | Length::Fill => *self,
+        }
+    }
+
+    fn get_absolute_unchecked(&self) -> f32 {
+        match self {
+            Length::Absolute(w) => *w,
+            _ => panic!("Length::get_absolute_unchecked() called on {}", self),
+        }
+    }
+}
+
+impl From<Length> for Width {
+    fn from(value: Length) -> Self {
+        match value {
+            Length::Absolute(w) => Width::Absolute(w),
+            Length::Percentage(w) => Width::Percentage(w),
+            Length::Em(w) => Width::Em(w),
+            Length::Fill => Width::Fill,
+        }
+    }
+}
+
+impl FromStr for Width {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Width, Self::Err> {
+        let Some((base, bounds)) = s.trim().split_once('(') else {
+            return Ok(Width::from(s.parse::<Length>()?));
+        };
+
+        let bounds = bounds
+            .strip_suffix(')')
+            .ok_or_else(|| format!("invalid width: {}, missing ')'", s))?;
+
+        let mut min = None;
+        let mut max = None;
+
+        for bound in bounds.split(',') {
+            let (key, value) = bound
+                .split_once('=')
+                .ok_or_else(|| format!("invalid width bound: {}", bound))?;
+            let value = value.parse::<Length>()?;
+            if value == Length::Fill {
+                return Err(format!("width bounds cannot be fill: {}", s));
+            }
+            match key.trim() {
+                "min" => min = Some(value),
+                "max" => max = Some(value),
+                key => return Err(format!("unknown width bound: {}", key)),
+            }
+        }
+
+        Ok(Width::Bounded {
+            width: base.parse::<Length>()?,
+            min,
+            max,
+        })
+    }
+}
+
+impl std::fmt::Display for Width {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Width::Absolute(w) => write!(f, "{}", Length::Absolute(*w)),
+            Width::Percentage(w) => write!(f, "{}", Length::Percentage(*w)),
+            Width::Em(w) => write!(f, "{}", Length::Em(*w)),
+            Width::Fill => write!(f, "{}", Length::Fill),
+            Width::Bounded { width, min, max } => {
+                let bounds = [("min", min), ("max", max)]
+                    .iter()
+                    .filter_map(|(key, bound)| bound.map(|b| format!("{}={}", key, b)))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{}({})", width, bounds)
+            }
+        }
+    }
 }
 
 impl<'de> Visitor<'de> for Width {
@@ -19,21 +166,7 @@ impl<'de> Visitor<'de> for Width {
     where
         E: serde::de::Error,
     {
-        if let Some(stripped) = v.strip_suffix('%') {
-            let w = stripped
-                .parse::<f32>()
-                .map_err(|_| E::custom("invalid percentage"))?;
-            Ok(Width::Percentage(w))
-        } else if let Some(stripped) = v.strip_suffix("px") {
-            let w = stripped
-                .parse::<f32>()
-                .map_err(|_| E::custom("invalid pixel width"))?;
-            Ok(Width::Absolute(w))
-        } else if v == "fill" {
-            Ok(Width::Fill)
-        } else {
-            Err(E::custom("invalid width"))
-        }
+        v.parse::<Width>().map_err(E::custom)
     }
 }
 
@@ -51,38 +184,150 @@ impl Serialize for Width {
     where
         S: serde::Serializer,
     {
-        match self {
-            Width::Absolute(w) => serializer.serialize_str(&format!("{}px", w)),
-            Width::Percentage(w) => serializer.serialize_str(&format!("{}%", w)),
-            Width::Fill => serializer.serialize_str("fill"),
-        }
+        serializer.serialize_str(&self.to_string())
     }
 }
 
 impl Width {
     pub fn is_fixed(&self) -> bool {
-        !matches!(self, Width::Fill)
+        !matches!(
+            self,
+            Width::Fill
+                | Width::Bounded {
+                    width: Length::Fill,
+                    ..
+                }
+        )
     }
 
     pub fn get_fixed(&self) -> Option<f32> {
         match self {
-            Width::Absolute(w) | Width::Percentage(w) => Some(*w),
+            Width::Absolute(w) | Width::Percentage(w) | Width::Em(w) => Some(*w),
+            Width::Bounded { width, .. } => match width {
+                Length::Absolute(w) | Length::Percentage(w) | Length::Em(w) => Some(*w),
+                Length::Fill => None,
+            },
             Width::Fill => None,
         }
     }
 
     pub fn get_fixed_unchecked(&self) -> f32 {
+        self.get_fixed()
+            .unwrap_or_else(|| panic!("Width::get_fixed_unchecked() called on {}", self))
+    }
+
+    /// Resolves percentages against `total_width` and ems against `font_size`.
+    /// Bounded widths are clamped right away unless they are filling, in which
+    /// case the clamping happens in `Width::bound`.
+    pub fn scale(&self, total_width: f32, font_size: f32) -> Width {
         match self {
-            Width::Absolute(w) | Width::Percentage(w) => *w,
-            Width::Fill => panic!("Width::get_fixed_unchecked() called on Width::Fill"),
+            Width::Percentage(w) => Width::Absolute(*w / 100.0 * total_width),
+            Width::Em(w) => Width::Absolute(*w * font_size),
+            Width::Absolute(_) | Width::Fill => *self,
+            Width::Bounded { width, min, max } => {
+                let scaled = Width::Bounded {
+                    width: width.scale(total_width, font_size),
+                    min: min.map(|m| m.scale(total_width, font_size)),
+                    max: max.map(|m| m.scale(total_width, font_size)),
+                };
+                match width {
+                    Length::Fill => scaled,
+                    _ => Width::Absolute(scaled.clamp(scaled.get_fixed_unchecked())),
+                }
+            }
         }
     }
 
-    pub fn scale(&self, total_width: f32) -> Width {
+    /// Fits the width into the `width` available to it. Filling widths take up
+    /// all of the available space within their bounds.
+    pub fn bound(&self, width: f32) -> Width {
         match self {
-            Width::Percentage(w) => Width::Absolute(*w / 100.0 * total_width),
-            Width::Absolute(_) => *self,
-            Width::Fill => Width::Fill,
+            Width::Absolute(w) => Width::Absolute(f32::min(*w, width)),
+            Width::Fill => Width::Absolute(width),
+            Width::Bounded {
+                width: Length::Fill,
+                min,
+                max,
+            } => Width::Bounded {
+                width: Length::Absolute(self.clamp(width)),
+                min: *min,
+                max: *max,
+            },
+            Width::Bounded { .. } => Width::Absolute(f32::min(self.get_fixed_unchecked(), width)),
+            Width::Percentage(_) | Width::Em(_) => {
+                unreachable!("Width::bound: Cannot bound width for non-unitized widths!")
+            }
         }
     }
+
+    /// Clamps `width` between the bounds of a scaled width.
+    pub fn clamp(&self, width: f32) -> f32 {
+        match self {
+            Width::Bounded { min, max, .. } => {
+                let width = max.map_or(width, |m| f32::min(width, m.get_absolute_unchecked()));
+                min.map_or(width, |m| f32::max(width, m.get_absolute_unchecked()))
+            }
+            _ => width,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let widths: Vec<Width> =
+            serde_json::from_str(r#"["30%", "12px", "12pt", "1in", "25.4mm", "2em", "fill"]"#)
+                .unwrap();
+        assert_eq!(
+            widths,
+            vec![
+                Width::Percentage(30.0),
+                Width::Absolute(12.0),
+                Width::Absolute(12.0),
+                Width::Absolute(72.0),
+                Width::Absolute(72.0),
+                Width::Em(2.0),
+                Width::Fill,
+            ]
+        );
+
+        let width: Width = serde_json::from_str(r#""fill(min=1in, max=50%)""#).unwrap();
+        assert_eq!(
+            width,
+            Width::Bounded {
+                width: Length::Fill,
+                min: Some(Length::Absolute(72.0)),
+                max: Some(Length::Percentage(50.0)),
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&width).unwrap(),
+            r#""fill(min=72pt, max=50%)""#
+        );
+
+        assert!(serde_json::from_str::<Width>(r#""12furlongs""#).is_err());
+        assert!(serde_json::from_str::<Width>(r#""fill(min=fill)""#).is_err());
+    }
+
+    #[test]
+    fn test_scale_and_bound() {
+        assert_eq!(Width::Em(2.0).scale(500.0, 12.0), Width::Absolute(24.0));
+        assert_eq!(
+            Width::Percentage(50.0).scale(500.0, 12.0),
+            Width::Absolute(250.0)
+        );
+
+        let width: Width = "50%(max=100pt)".parse().unwrap();
+        assert_eq!(width.scale(500.0, 12.0), Width::Absolute(100.0));
+
+        let width: Width = "fill(min=100pt, max=50%)".parse().unwrap();
+        let scaled = width.scale(500.0, 12.0);
+        assert_eq!(scaled.bound(400.0).get_fixed_unchecked(), 250.0);
+        assert_eq!(scaled.bound(200.0).get_fixed_unchecked(), 200.0);
+        assert_eq!(scaled.bound(50.0).get_fixed_unchecked(), 100.0);
+        assert_eq!(scaled.clamp(10.0), 100.0);
+    }
 }